@@ -0,0 +1,357 @@
+//! A language server providing formatting for SQL files, speaking LSP over stdio.
+//!
+//! Options are read from the `sqlformat.toml` closest to each document, then from the
+//! client's configuration (`initializationOptions` or `workspace/didChangeConfiguration`,
+//! optionally nested under a `sqlformat` key), then from the editor's indentation settings.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::process;
+
+use serde_json::{Value, json};
+use sqlformat::{Config, Indent, QueryParams, TextEdit};
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+struct Server {
+    /// The text of the open documents, by URI
+    documents: HashMap<String, String>,
+    /// The options from the client's configuration
+    settings: Config,
+    shutdown: bool,
+}
+
+type RequestResult = Result<Value, (i64, String)>;
+
+impl Server {
+    fn handle_request(&mut self, method: &str, params: &Value) -> RequestResult {
+        match method {
+            "initialize" => {
+                self.settings = settings(&params["initializationOptions"]);
+                Ok(json!({
+                    "capabilities": {
+                        // Full synchronization, every change sends the whole document
+                        "textDocumentSync": 1,
+                        "documentFormattingProvider": true,
+                        "documentRangeFormattingProvider": true,
+                        "documentOnTypeFormattingProvider": { "firstTriggerCharacter": ";" },
+                    },
+                    "serverInfo": {
+                        "name": "sqlformat-lsp",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/formatting" => {
+                let (uri, text) = self.document(params)?;
+                let config = self.config(uri, &params["options"]);
+                let formatted =
+                    sqlformat::format(text, &QueryParams::None, &config.format_options());
+                let edits = if formatted == text {
+                    Vec::new()
+                } else {
+                    vec![TextEdit {
+                        range: 0..text.len(),
+                        new_text: formatted,
+                    }]
+                };
+                Ok(text_edits(text, &edits))
+            }
+            "textDocument/rangeFormatting" => {
+                let (uri, text) = self.document(params)?;
+                let config = self.config(uri, &params["options"]);
+                let range = offset(text, &params["range"]["start"])?
+                    ..offset(text, &params["range"]["end"])?;
+                let edits = sqlformat::format_range(
+                    text,
+                    range,
+                    &QueryParams::None,
+                    &config.format_options(),
+                );
+                Ok(text_edits(text, &edits))
+            }
+            "textDocument/onTypeFormatting" => {
+                let (uri, text) = self.document(params)?;
+                let config = self.config(uri, &params["options"]);
+                // The position is just after the typed character, format the statement it ends
+                let end = offset(text, &params["position"])?;
+                let typed = params["ch"].as_str().map_or(0, str::len);
+                let range = end.saturating_sub(typed)..end;
+                let edits = sqlformat::format_range(
+                    text,
+                    range,
+                    &QueryParams::None,
+                    &config.format_options(),
+                );
+                Ok(text_edits(text, &edits))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method '{method}'"))),
+        }
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Value) {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.to_string(), text.to_string());
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|c| c.last()?["text"].as_str()) {
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+            }
+            "workspace/didChangeConfiguration" => {
+                self.settings = settings(&params["settings"]);
+            }
+            _ => {}
+        }
+    }
+
+    fn document<'a>(&'a self, params: &'a Value) -> Result<(&'a str, &'a str), (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match self.documents.get(uri) {
+            Some(text) => Ok((uri, text)),
+            None => Err((INVALID_PARAMS, format!("Unknown document '{uri}'"))),
+        }
+    }
+
+    /// Layers the closest `sqlformat.toml` over the client's settings over the editor's
+    /// indentation
+    fn config(&self, uri: &str, formatting: &Value) -> Config {
+        let indent = match formatting["insertSpaces"].as_bool() {
+            Some(false) => Some(Indent::Tabs),
+            Some(true) => formatting["tabSize"]
+                .as_u64()
+                .and_then(|size| u8::try_from(size).ok())
+                .map(Indent::Spaces),
+            None => None,
+        };
+        let mut config = layer(
+            Config {
+                indent,
+                ..Config::default()
+            },
+            self.settings.clone(),
+        );
+
+        if let Some(dir) = uri_to_path(uri).as_deref().and_then(|path| path.parent()) {
+            match Config::discover(dir) {
+                Ok(Some(discovered)) => config = layer(config, discovered),
+                Ok(None) => {}
+                Err(err) => eprintln!("Error reading configuration {err}"),
+            }
+        }
+        config
+    }
+}
+
+/// Returns `base` with the options set in `over` replaced
+fn layer(base: Config, over: Config) -> Config {
+    Config {
+        indent: over.indent.or(base.indent),
+        uppercase: over.uppercase.or(base.uppercase),
+        lines_between_queries: over.lines_between_queries.or(base.lines_between_queries),
+        ignore_case_convert: over.ignore_case_convert.or(base.ignore_case_convert),
+        inline: over.inline.or(base.inline),
+        max_inline_block: over.max_inline_block.or(base.max_inline_block),
+        max_inline_arguments: over.max_inline_arguments.or(base.max_inline_arguments),
+        max_inline_top_level: over.max_inline_top_level.or(base.max_inline_top_level),
+        max_line_width: over.max_line_width.or(base.max_line_width),
+        comma_position: over.comma_position.or(base.comma_position),
+        clause_layout: over.clause_layout.or(base.clause_layout),
+        joins_as_top_level: over.joins_as_top_level.or(base.joins_as_top_level),
+        dialect: over.dialect.or(base.dialect),
+        format_function_bodies: over.format_function_bodies.or(base.format_function_bodies),
+    }
+}
+
+/// Reads the client's settings, which use the same keys as `sqlformat.toml`
+fn settings(value: &Value) -> Config {
+    let value = value.get("sqlformat").unwrap_or(value);
+    if value.is_null() {
+        return Config::default();
+    }
+    serde_json::from_value(value.clone()).unwrap_or_else(|err| {
+        eprintln!("Error reading client settings: {err}");
+        Config::default()
+    })
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    // `file:///C:/dir` names the Windows path `C:/dir`
+    #[cfg(windows)]
+    let path = match path.strip_prefix('/') {
+        Some(stripped) if stripped.as_bytes().get(1) == Some(&b':') => stripped.to_string(),
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
+
+/// Converts an LSP position, whose character is counted in UTF-16 code units, to a byte
+/// offset in `text`
+fn offset(text: &str, position: &Value) -> Result<usize, (i64, String)> {
+    let (Some(line), Some(character)) = (position["line"].as_u64(), position["character"].as_u64())
+    else {
+        return Err((INVALID_PARAMS, format!("Invalid position {position}")));
+    };
+
+    let mut line_start = 0;
+    for _ in 0..line {
+        match text[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => return Ok(text.len()),
+        }
+    }
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return Ok(line_start + i);
+        }
+        units += c.len_utf16() as u64;
+    }
+    Ok(text.len())
+}
+
+fn position(text: &str, offset: usize) -> Value {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    json!({
+        "line": before.matches('\n').count(),
+        "character": before[line_start..].encode_utf16().count(),
+    })
+}
+
+fn range(text: &str, range: &Range<usize>) -> Value {
+    json!({ "start": position(text, range.start), "end": position(text, range.end) })
+}
+
+fn text_edits(text: &str, edits: &[TextEdit]) -> Value {
+    edits
+        .iter()
+        .map(|edit| json!({ "range": range(text, &edit.range), "newText": edit.new_text }))
+        .collect()
+}
+
+/// Reads the next message, `None` once the client closed the connection
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Result<Value, String>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Ok(Some(Err("Missing Content-Length header".to_string())));
+    };
+
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(
+        serde_json::from_slice(&body).map_err(|err| err.to_string()),
+    ))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+fn run() -> io::Result<i32> {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    let mut server = Server {
+        documents: HashMap::new(),
+        settings: Config::default(),
+        shutdown: false,
+    };
+
+    while let Some(message) = read_message(&mut input)? {
+        let message = match message {
+            Ok(message) => message,
+            Err(err) => {
+                let error = json!({ "code": PARSE_ERROR, "message": err });
+                write_message(
+                    &mut output,
+                    &json!({ "jsonrpc": "2.0", "id": null, "error": error }),
+                )?;
+                continue;
+            }
+        };
+        let Some(method) = message["method"].as_str() else {
+            // Responses to requests we never send
+            continue;
+        };
+        let params = &message["params"];
+
+        match message.get("id") {
+            Some(id) => {
+                let response = match server.handle_request(method, params) {
+                    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": code, "message": message },
+                    }),
+                };
+                write_message(&mut output, &response)?;
+            }
+            None if method == "exit" => return Ok(if server.shutdown { 0 } else { 1 }),
+            None => server.handle_notification(method, params),
+        }
+    }
+    Ok(1)
+}
+
+fn main() {
+    match run() {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+    }
+}
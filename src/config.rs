@@ -0,0 +1,567 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use winnow::Result;
+use winnow::ascii::{digit1, space0, till_line_ending};
+use winnow::combinator::{alt, delimited, opt, preceded, repeat, terminated};
+use winnow::error::ParserError;
+use winnow::prelude::*;
+use winnow::token::{any, one_of, take, take_till, take_while};
+
+use crate::{ClauseLayout, CommaPosition, Dialect, FormatOptions, Indent};
+
+/// Formatting options loaded from a `sqlformat.toml` file, also usable as an owned
+/// counterpart of [`FormatOptions`].
+///
+/// Every field maps onto the [`FormatOptions`] field of the same name and is left unset
+/// when the file doesn't mention it, so the values can be layered over other sources.
+/// With the `serde` feature the same keys are used in any format, unset fields are
+/// skipped when serializing:
+///
+/// ```toml
+/// indent = 4                  # number of spaces, or "tabs"
+/// uppercase = true
+/// lines_between_queries = 2
+/// ignore_case_convert = ["from", "where"]
+/// inline = false
+/// max_inline_block = 50
+/// max_inline_arguments = 40
+/// max_inline_top_level = 40
+/// max_line_width = 100
+/// comma_position = "leading"     # or "trailing"
+/// clause_layout = "river"         # "indented", "river" or "compact"
+/// joins_as_top_level = true
+/// dialect = "postgresql"
+/// format_function_bodies = false
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct Config {
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub indent: Option<Indent>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub uppercase: Option<bool>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub lines_between_queries: Option<u8>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub ignore_case_convert: Option<Vec<String>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub inline: Option<bool>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub max_inline_block: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub max_inline_arguments: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub max_inline_top_level: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub max_line_width: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub comma_position: Option<CommaPosition>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub clause_layout: Option<ClauseLayout>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub joins_as_top_level: Option<bool>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub dialect: Option<Dialect>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub format_function_bodies: Option<bool>,
+}
+
+impl Config {
+    /// The name of the configuration file looked up by [`Config::discover`]
+    pub const FILE_NAME: &'static str = "sqlformat.toml";
+
+    /// Parses the contents of a configuration file
+    pub fn parse(source: &str) -> std::result::Result<Self, ConfigError> {
+        let mut config = Config::default();
+        let mut seen: Vec<String> = Vec::new();
+        let mut input = source;
+        let error = |input: &str, message: String| ConfigError {
+            path: None,
+            line: source[..source.len() - input.len()].matches('\n').count() + 1,
+            message,
+        };
+
+        loop {
+            let _ = blank.parse_next(&mut input);
+            if input.is_empty() {
+                break;
+            }
+            if input.starts_with('[') {
+                return Err(error(input, "tables are not supported".to_string()));
+            }
+
+            let line_start = input;
+            let key = key
+                .parse_next(&mut input)
+                .map_err(|_| error(input, "expected a key".to_string()))?;
+            let value = preceded((space0, '=', space0), value)
+                .parse_next(&mut input)
+                .map_err(|_| error(input, format!("expected a value for `{key}`")))?;
+            (space0, opt(comment), alt((line_ending, eof)))
+                .parse_next(&mut input)
+                .map_err(|_| error(input, format!("unexpected characters after `{key}`")))?;
+
+            if seen.contains(&key) {
+                return Err(error(line_start, format!("duplicate key `{key}`")));
+            }
+            config
+                .set(&key, value)
+                .map_err(|message| error(line_start, message))?;
+            seen.push(key);
+        }
+
+        Ok(config)
+    }
+
+    /// Reads and parses a configuration file
+    pub fn from_file(path: &Path) -> std::result::Result<Self, ConfigError> {
+        let source = fs::read_to_string(path).map_err(|err| ConfigError {
+            path: Some(path.to_path_buf()),
+            line: 0,
+            message: err.to_string(),
+        })?;
+        Self::parse(&source).map_err(|err| ConfigError {
+            path: Some(path.to_path_buf()),
+            ..err
+        })
+    }
+
+    /// Finds the closest `sqlformat.toml`, looking in `start` (or its directory when it is a
+    /// file) and then in each of its parent directories
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        let dir = if start.is_file() {
+            start.parent()?
+        } else {
+            start
+        };
+        dir.ancestors()
+            .map(|dir| dir.join(Self::FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Loads the closest `sqlformat.toml` as located by [`Config::find`], if any
+    pub fn discover(start: &Path) -> std::result::Result<Option<Self>, ConfigError> {
+        Self::find(start)
+            .map(|path| Self::from_file(&path))
+            .transpose()
+    }
+
+    /// Builds the [`FormatOptions`] described by this configuration, unset fields keep their
+    /// default value
+    pub fn format_options(&self) -> FormatOptions<'_> {
+        let defaults = FormatOptions::default();
+        FormatOptions {
+            indent: self.indent.unwrap_or(defaults.indent),
+            uppercase: self.uppercase.or(defaults.uppercase),
+            lines_between_queries: self
+                .lines_between_queries
+                .unwrap_or(defaults.lines_between_queries),
+            ignore_case_convert: self
+                .ignore_case_convert
+                .as_ref()
+                .map(|words| words.iter().map(String::as_str).collect())
+                .or(defaults.ignore_case_convert),
+            inline: self.inline.unwrap_or(defaults.inline),
+            max_inline_block: self.max_inline_block.unwrap_or(defaults.max_inline_block),
+            max_inline_arguments: self.max_inline_arguments.or(defaults.max_inline_arguments),
+            max_inline_top_level: self.max_inline_top_level.or(defaults.max_inline_top_level),
+            max_line_width: self.max_line_width.or(defaults.max_line_width),
+            comma_position: self.comma_position.unwrap_or(defaults.comma_position),
+            clause_layout: self.clause_layout.unwrap_or(defaults.clause_layout),
+            joins_as_top_level: self
+                .joins_as_top_level
+                .unwrap_or(defaults.joins_as_top_level),
+            dialect: self.dialect.unwrap_or(defaults.dialect),
+            format_function_bodies: self
+                .format_function_bodies
+                .unwrap_or(defaults.format_function_bodies),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: Value) -> std::result::Result<(), String> {
+        let invalid = |expected: &str| format!("invalid value for `{key}`: expected {expected}");
+
+        match key {
+            "indent" => {
+                self.indent = Some(match value {
+                    Value::Integer(spaces) => Indent::Spaces(
+                        u8::try_from(spaces)
+                            .map_err(|_| invalid("a number of spaces or \"tabs\""))?,
+                    ),
+                    Value::String(s) if s == "tabs" => Indent::Tabs,
+                    _ => return Err(invalid("a number of spaces or \"tabs\"")),
+                })
+            }
+            "uppercase" => {
+                self.uppercase = Some(value.boolean().ok_or_else(|| invalid("a boolean"))?)
+            }
+            "lines_between_queries" => {
+                self.lines_between_queries = Some(
+                    value
+                        .integer()
+                        .and_then(|n| u8::try_from(n).ok())
+                        .ok_or_else(|| invalid("a number between 0 and 255"))?,
+                )
+            }
+            "ignore_case_convert" => {
+                let Value::Array(values) = value else {
+                    return Err(invalid("an array of strings"));
+                };
+                self.ignore_case_convert = Some(
+                    values
+                        .into_iter()
+                        .map(|value| match value {
+                            Value::String(s) => Ok(s),
+                            _ => Err(invalid("an array of strings")),
+                        })
+                        .collect::<std::result::Result<_, _>>()?,
+                );
+            }
+            "inline" => self.inline = Some(value.boolean().ok_or_else(|| invalid("a boolean"))?),
+            "max_inline_block" => {
+                self.max_inline_block = Some(value.size().ok_or_else(|| invalid("a length"))?)
+            }
+            "max_inline_arguments" => {
+                self.max_inline_arguments = Some(value.size().ok_or_else(|| invalid("a length"))?)
+            }
+            "max_inline_top_level" => {
+                self.max_inline_top_level = Some(value.size().ok_or_else(|| invalid("a length"))?)
+            }
+            "max_line_width" => {
+                self.max_line_width = Some(value.size().ok_or_else(|| invalid("a length"))?)
+            }
+            "comma_position" => {
+                self.comma_position = Some(match value {
+                    Value::String(s) if s == "trailing" => CommaPosition::Trailing,
+                    Value::String(s) if s == "leading" => CommaPosition::Leading,
+                    _ => return Err(invalid("\"trailing\" or \"leading\"")),
+                })
+            }
+            "clause_layout" => {
+                self.clause_layout = Some(match value {
+                    Value::String(s) if s == "indented" => ClauseLayout::Indented,
+                    Value::String(s) if s == "river" => ClauseLayout::River,
+                    Value::String(s) if s == "compact" => ClauseLayout::Compact,
+                    _ => return Err(invalid("\"indented\", \"river\" or \"compact\"")),
+                })
+            }
+            "joins_as_top_level" => {
+                self.joins_as_top_level = Some(value.boolean().ok_or_else(|| invalid("a boolean"))?)
+            }
+            "dialect" => {
+                let Value::String(name) = value else {
+                    return Err(invalid("a dialect name"));
+                };
+                self.dialect = Some(
+                    name.parse()
+                        .map_err(|err| format!("invalid value for `{key}`: {err}"))?,
+                );
+            }
+            "format_function_bodies" => {
+                self.format_function_bodies =
+                    Some(value.boolean().ok_or_else(|| invalid("a boolean"))?)
+            }
+            _ => return Err(format!("unknown key `{key}`")),
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&FormatOptions<'_>> for Config {
+    fn from(options: &FormatOptions<'_>) -> Self {
+        Config {
+            indent: Some(options.indent),
+            uppercase: options.uppercase,
+            lines_between_queries: Some(options.lines_between_queries),
+            ignore_case_convert: options
+                .ignore_case_convert
+                .as_ref()
+                .map(|words| words.iter().map(|word| word.to_string()).collect()),
+            inline: Some(options.inline),
+            max_inline_block: Some(options.max_inline_block),
+            max_inline_arguments: options.max_inline_arguments,
+            max_inline_top_level: options.max_inline_top_level,
+            max_line_width: options.max_line_width,
+            comma_position: Some(options.comma_position),
+            clause_layout: Some(options.clause_layout),
+            joins_as_top_level: Some(options.joins_as_top_level),
+            dialect: Some(options.dialect),
+            format_function_bodies: Some(options.format_function_bodies),
+        }
+    }
+}
+
+/// An error reading or parsing a configuration file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// The configuration file, if the error came from one
+    pub path: Option<PathBuf>,
+    /// The 1-based line of the error, 0 when it isn't tied to a line
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+        if self.line > 0 {
+            write!(f, "line {}: ", self.line)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The subset of TOML values used by the configuration
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn boolean(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn size(&self) -> Option<usize> {
+        self.integer().and_then(|n| usize::try_from(n).ok())
+    }
+}
+
+fn eof<'i>(input: &mut &'i str) -> Result<&'i str> {
+    winnow::combinator::eof.parse_next(input)
+}
+
+fn line_ending<'i>(input: &mut &'i str) -> Result<&'i str> {
+    alt(("\n", "\r\n")).parse_next(input)
+}
+
+fn comment<'i>(input: &mut &'i str) -> Result<&'i str> {
+    ('#', till_line_ending).take().parse_next(input)
+}
+
+/// Whitespace, newlines and comments
+fn blank<'i>(input: &mut &'i str) -> Result<()> {
+    repeat(
+        0..,
+        alt((take_while(1.., [' ', '\t', '\r', '\n']), comment)),
+    )
+    .parse_next(input)
+}
+
+fn key(input: &mut &str) -> Result<String> {
+    alt((
+        take_while(1.., |c: char| {
+            c.is_ascii_alphanumeric() || c == '_' || c == '-'
+        })
+        .map(str::to_string),
+        basic_string,
+        literal_string,
+    ))
+    .parse_next(input)
+}
+
+fn value(input: &mut &str) -> Result<Value> {
+    alt((
+        basic_string.map(Value::String),
+        literal_string.map(Value::String),
+        boolean.map(Value::Boolean),
+        integer.map(Value::Integer),
+        array.map(Value::Array),
+    ))
+    .parse_next(input)
+}
+
+fn basic_string(input: &mut &str) -> Result<String> {
+    '"'.parse_next(input)?;
+    let mut value = String::new();
+    loop {
+        match any.parse_next(input)? {
+            '"' => return Ok(value),
+            '\\' => value.push(match any.parse_next(input)? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '"' => '"',
+                '\\' => '\\',
+                'u' => take(4usize)
+                    .verify_map(|hex| u32::from_str_radix(hex, 16).ok().and_then(char::from_u32))
+                    .parse_next(input)?,
+                _ => return Err(ParserError::from_input(input)),
+            }),
+            '\n' => return Err(ParserError::from_input(input)),
+            c => value.push(c),
+        }
+    }
+}
+
+fn literal_string(input: &mut &str) -> Result<String> {
+    delimited('\'', take_till(0.., ['\'', '\n']), '\'')
+        .map(str::to_string)
+        .parse_next(input)
+}
+
+fn boolean(input: &mut &str) -> Result<bool> {
+    terminated(
+        alt(("true".value(true), "false".value(false))),
+        winnow::combinator::not(one_of(|c: char| c.is_ascii_alphanumeric() || c == '_')),
+    )
+    .parse_next(input)
+}
+
+fn integer(input: &mut &str) -> Result<i64> {
+    (
+        opt(one_of(['+', '-'])),
+        digit1,
+        repeat::<_, _, (), _, _>(0.., ('_', digit1)),
+    )
+        .take()
+        .verify_map(|digits: &str| digits.replace('_', "").parse().ok())
+        .parse_next(input)
+}
+
+fn array(input: &mut &str) -> Result<Vec<Value>> {
+    '['.parse_next(input)?;
+    let mut values = Vec::new();
+    loop {
+        blank.parse_next(input)?;
+        if opt(']').parse_next(input)?.is_some() {
+            return Ok(values);
+        }
+        values.push(value.parse_next(input)?);
+        blank.parse_next(input)?;
+        if opt(',').parse_next(input)?.is_none() {
+            blank.parse_next(input)?;
+            ']'.parse_next(input)?;
+            return Ok(values);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_every_option() {
+        let config = Config::parse(
+            r#"
+            # Formatting for the migrations
+            indent = 4
+            uppercase = true # trailing comment
+            lines_between_queries = 2
+            ignore_case_convert = [
+                "from",
+                'where', # comment inside the array
+            ]
+            inline = false
+            max_inline_block = 1_000
+            max_inline_arguments = 40
+            max_inline_top_level = 40
+            max_line_width = 100
+            comma_position = "leading"
+            clause_layout = "river"
+            joins_as_top_level = true
+            "dialect" = "PostgreSQL"
+            format_function_bodies = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                indent: Some(Indent::Spaces(4)),
+                uppercase: Some(true),
+                lines_between_queries: Some(2),
+                ignore_case_convert: Some(vec!["from".to_string(), "where".to_string()]),
+                inline: Some(false),
+                max_inline_block: Some(1000),
+                max_inline_arguments: Some(40),
+                max_inline_top_level: Some(40),
+                max_line_width: Some(100),
+                comma_position: Some(CommaPosition::Leading),
+                clause_layout: Some(ClauseLayout::River),
+                joins_as_top_level: Some(true),
+                dialect: Some(Dialect::PostgreSql),
+                format_function_bodies: Some(true),
+            }
+        );
+
+        let options = config.format_options();
+        assert_eq!(options.indent, Indent::Spaces(4));
+        assert_eq!(options.ignore_case_convert, Some(vec!["from", "where"]));
+        assert_eq!(options.dialect, Dialect::PostgreSql);
+
+        let config = Config::parse("indent = \"tabs\"\r\n").unwrap();
+        assert_eq!(config.format_options().indent, Indent::Tabs);
+        assert_eq!(config.format_options().max_inline_block, 50);
+    }
+
+    #[test]
+    fn it_reports_invalid_configurations() {
+        let cases = [
+            ("indnet = 4", 1, "unknown key `indnet`"),
+            (
+                "\nindent = -1",
+                2,
+                "invalid value for `indent`: expected a number of spaces or \"tabs\"",
+            ),
+            (
+                "uppercase = \"yes\"",
+                1,
+                "invalid value for `uppercase`: expected a boolean",
+            ),
+            (
+                "dialect = \"cobol\"",
+                1,
+                "invalid value for `dialect`: expected one of generic, postgresql, sqlserver, mysql, sqlite, oracle, bigquery, snowflake, clickhouse, duckdb, spark, trino, redshift",
+            ),
+            (
+                "comma_position = \"first\"",
+                1,
+                "invalid value for `comma_position`: expected \"trailing\" or \"leading\"",
+            ),
+            ("inline = true\ninline = false", 2, "duplicate key `inline`"),
+            (
+                "inline = true false",
+                1,
+                "unexpected characters after `inline`",
+            ),
+            ("inline =", 1, "expected a value for `inline`"),
+            ("\n\n[format]\nindent = 2", 3, "tables are not supported"),
+        ];
+
+        for (source, line, message) in cases {
+            let err = Config::parse(source).unwrap_err();
+            assert_eq!(
+                (err.line, err.message.as_str()),
+                (line, message),
+                "{source}"
+            );
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::tokenizer;
+use crate::{Dialect, Token, TokenKind};
+
+/// The error returned by [`try_format`](crate::try_format) when the query is malformed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatError {
+    /// Every problem found in the query, in source order
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A problem found in a query, located by the token it was detected on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    /// The byte range of the offending text in the query
+    pub span: Range<usize>,
+    /// The 1-based line the offending text starts on
+    pub line: usize,
+    /// The 1-based column the offending text starts at, counted in characters
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DiagnosticKind {
+    /// A quoted string or identifier without its closing quote
+    UnterminatedString,
+    /// A `/*` comment without its closing `*/`
+    UnterminatedComment,
+    /// A `)`, `]` or `END` without a matching opening token
+    UnmatchedClose,
+    /// A `(`, `[` or `CASE` that is never closed
+    UnclosedBlock,
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DiagnosticKind::UnterminatedString => "unterminated string",
+            DiagnosticKind::UnterminatedComment => "unterminated block comment",
+            DiagnosticKind::UnmatchedClose => "unmatched closing token",
+            DiagnosticKind::UnclosedBlock => "unclosed block",
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.kind)
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Opening tokens tracked while checking that blocks are balanced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Opener {
+    Paren,
+    Bracket,
+    Brace,
+    Case,
+    /// `BEGIN` may start a transaction rather than a block, so it is never reported as
+    /// unclosed but can still be closed by `END`
+    Begin,
+}
+
+pub(crate) fn check(
+    tokens: &[tokenizer::Token<'_>],
+    query_len: usize,
+    dialect: Dialect,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut stack: Vec<(Opener, &Token<'_>)> = Vec::new();
+    let diagnostic = |kind, token: &Token<'_>| Diagnostic {
+        kind,
+        span: token.span.clone(),
+        line: token.line,
+        column: token.column,
+    };
+
+    let positioned = crate::with_positions(tokens);
+    // Statements end on the tokens the formatter ends them on, whatever their text, such as
+    // Oracle's `/` or a MySQL `DELIMITER`
+    let (separators, tokens): (Vec<bool>, Vec<&Token<'_>>) = tokens
+        .iter()
+        .zip(&positioned)
+        .filter(|(_, t)| t.kind != TokenKind::Whitespace)
+        .map(|(raw, t)| (raw.alias == ";", t))
+        .unzip();
+    for (i, &token) in tokens.iter().enumerate() {
+        if opens_unterminated_string(&tokens[i..], dialect) {
+            diagnostics.push(Diagnostic {
+                span: token.span.start..query_len,
+                ..diagnostic(DiagnosticKind::UnterminatedString, token)
+            });
+            // Everything after the quote belongs to the string
+            break;
+        }
+        match token.kind {
+            TokenKind::BlockComment if token.text.len() < 4 || !token.text.ends_with("*/") => {
+                diagnostics.push(diagnostic(DiagnosticKind::UnterminatedComment, token));
+            }
+            _ if separators[i] => {
+                // Blocks never span statements, except for procedural BEGIN ... END bodies,
+                // which Oracle's `/` and MySQL's custom delimiters end as well
+                let ends_blocks = !token.text.starts_with(';');
+                while let Some(&(opener, open_token)) = stack.last() {
+                    if opener == Opener::Begin && !ends_blocks {
+                        break;
+                    }
+                    if opener != Opener::Begin {
+                        diagnostics.push(diagnostic(DiagnosticKind::UnclosedBlock, open_token));
+                    }
+                    stack.pop();
+                }
+            }
+            TokenKind::ReservedTopLevelNoIndent if token.text.eq_ignore_ascii_case("BEGIN") => {
+                stack.push((Opener::Begin, token));
+            }
+            TokenKind::OpenParen => {
+                let opener = match token.text {
+                    "(" => Opener::Paren,
+                    "[" => Opener::Bracket,
+                    "{" => Opener::Brace,
+                    _ => Opener::Case,
+                };
+                stack.push((opener, token));
+            }
+            TokenKind::CloseParen => {
+                let upper = token.text.to_ascii_uppercase();
+                // Procedural `END IF`, `END LOOP`, ... close statements that are not tracked
+                if upper == "END"
+                    && tokens.get(i + 1).is_some_and(|next| {
+                        ["IF", "LOOP", "WHILE", "REPEAT", "FOR"]
+                            .iter()
+                            .any(|word| next.text.eq_ignore_ascii_case(word))
+                    })
+                {
+                    continue;
+                }
+                let closes: &[Opener] = match upper.as_str() {
+                    ")" => &[Opener::Paren],
+                    "]" => &[Opener::Bracket],
+                    "}" => &[Opener::Brace],
+                    "END" => &[Opener::Case, Opener::Begin],
+                    _ => &[Opener::Case],
+                };
+
+                let Some(position) = stack.iter().rposition(|(o, _)| closes.contains(o)) else {
+                    diagnostics.push(diagnostic(DiagnosticKind::UnmatchedClose, token));
+                    continue;
+                };
+                for &(opener, open_token) in &stack[position + 1..] {
+                    if opener != Opener::Begin {
+                        diagnostics.push(diagnostic(DiagnosticKind::UnclosedBlock, open_token));
+                    }
+                }
+                stack.truncate(position);
+            }
+            _ => {}
+        }
+    }
+
+    diagnostics.extend(
+        stack
+            .iter()
+            .filter(|(opener, _)| *opener != Opener::Begin)
+            .map(|(_, token)| diagnostic(DiagnosticKind::UnclosedBlock, token)),
+    );
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    diagnostics
+}
+
+/// Whether the first of `tokens` starts a string that is never closed, which the tokenizer
+/// doesn't read as a string but splits into whatever tokens the rest of the text holds
+fn opens_unterminated_string(tokens: &[&Token<'_>], dialect: Dialect) -> bool {
+    let token = tokens[0];
+    match token.kind {
+        // A lone quote
+        TokenKind::Operator => {
+            matches!(token.text, "'" | "\"" | "`")
+                || (token.text == "[" && matches!(dialect, Dialect::SQLServer | Dialect::Sqlite))
+        }
+        // PostgreSQL's `$$` or `$tag$` dollar quote
+        TokenKind::ReservedTopLevelNoIndent | TokenKind::Placeholder => {
+            dialect == Dialect::PostgreSql
+                && token.text.len() > 1
+                && token.text.starts_with('$')
+                && token.text.ends_with('$')
+        }
+        // Oracle's `q'[...]'` quoting, followed by the text of its opening quote
+        TokenKind::Word => {
+            dialect == Dialect::Oracle
+                && token.text.eq_ignore_ascii_case("q")
+                && tokens.get(1).is_some_and(|next| {
+                    let mut quote = next.text.chars();
+                    next.span.start == token.span.end
+                        && quote.next() == Some('\'')
+                        && quote
+                            .next()
+                            .is_some_and(|delimiter| !delimiter.is_whitespace())
+                })
+        }
+        _ => false,
+    }
+}
@@ -1,10 +1,11 @@
 use std::borrow::Cow;
+use std::collections::HashSet;
 
 use crate::indentation::Indentation;
 use crate::inline_block::InlineBlock;
 use crate::params::Params;
-use crate::tokenizer::{Token, TokenKind};
-use crate::{FormatOptions, QueryParams, SpanInfo};
+use crate::tokenizer::{Token, TokenKind, tokenize_function_body};
+use crate::{ClauseLayout, CommaPosition, Dialect, FormatOptions, QueryParams, SpanInfo};
 
 // -- fmt: off
 // -- fmt: on
@@ -58,7 +59,13 @@ pub(crate) fn format(
     let mut formatted_query = String::new();
     let mut is_fmt_enabled = true;
     let mut is_prev_token_fmt_switch = false;
-    for (index, token) in tokens.iter().enumerate() {
+    // Lines are only fitted in a width when queries aren't written on a single line
+    let width = options.max_line_width.filter(|_| !options.inline);
+    // The groups written on a single line on the current line
+    let mut groups: Vec<Group<'_>> = Vec::new();
+    let mut index = 0;
+    while let Some(token) = tokens.get(index) {
+        index += 1;
         if is_prev_token_fmt_switch {
             is_prev_token_fmt_switch = false;
             continue;
@@ -70,12 +77,28 @@ pub(crate) fn format(
                 continue;
             }
         }
-        formatter.index = index;
+        formatter.index = index - 1;
 
         if !is_fmt_enabled {
             formatter.format_no_change(token, &mut formatted_query);
             continue;
         }
+        // Only trailing whitespace is ever removed from the query, so whatever comes before it
+        // is there to stay
+        let kept = formatted_query.trim_end().len();
+        let group = width
+            .and_then(|_| formatter.group_end(token))
+            .map(|end| Group {
+                index: formatter.index,
+                end,
+                start: 0,
+                formatter: formatter.clone(),
+                kept,
+                trailing: formatted_query[kept..].to_string(),
+            });
+        if formatter.ends_top_clause(token) {
+            formatter.add_new_line(&mut formatted_query);
+        }
 
         match token.kind {
             TokenKind::Whitespace => {
@@ -110,6 +133,19 @@ pub(crate) fn format(
                 formatter.format_with_spaces(token, &mut formatted_query);
                 formatter.indentation.set_previous_reserved(token);
             }
+            TokenKind::BlockStart => {
+                formatter.format_block_start(token, &mut formatted_query);
+            }
+            TokenKind::BlockMiddle => {
+                formatter.format_block_middle(token, &mut formatted_query);
+            }
+            TokenKind::BlockEnd => {
+                formatter.format_block_end(token, &mut formatted_query);
+            }
+            TokenKind::ReservedStatement => {
+                formatter.format_statement_start(token, &mut formatted_query);
+                formatter.indentation.set_previous_reserved(token);
+            }
             TokenKind::OpenParen => {
                 formatter.format_opening_parentheses(token, &mut formatted_query);
             }
@@ -122,7 +158,10 @@ pub(crate) fn format(
             TokenKind::TypeSpecifier => {
                 formatter.format_type_specifier(token, &mut formatted_query);
             }
-            _ => match token.value {
+            TokenKind::String if formatter.is_function_body(token) => {
+                formatter.format_function_body(token, &mut formatted_query);
+            }
+            _ => match token.alias {
                 "," => {
                     formatter.format_comma(token, &mut formatted_query);
                 }
@@ -178,10 +217,50 @@ pub(crate) fn format(
             };
             anstream::eprintln!("{k}{:21}{rk}: {d}{:50}{rd} {line}", kind, value);
         }
+
+        let Some(width) = width.filter(|_| token.kind != TokenKind::Whitespace) else {
+            continue;
+        };
+        let start = formatted_query.len() - formatted_query[kept..].trim_start().len();
+        groups.extend(group.map(|group| Group { start, ..group }));
+        if let Some(position) = formatter.fit_line(&mut formatted_query, kept, width, &mut groups) {
+            // The group is written again from its start, broken over lines
+            let group = groups.drain(position..).next().unwrap();
+            let breaks = std::mem::take(&mut formatter.breaks);
+            formatter = group.formatter;
+            formatter.breaks = breaks;
+            formatter.breaks.insert(group.index);
+            formatted_query.truncate(group.kept);
+            formatted_query.push_str(&group.trailing);
+            index = group.index;
+        }
     }
-    formatted_query.trim().to_string()
+    // Whitespace is trimmed, except for the padding of a first keyword right-aligned on the
+    // river
+    let formatted_query = if formatter.aligns_clauses() {
+        formatted_query.trim_start_matches('\n')
+    } else {
+        formatted_query.trim_start()
+    };
+    formatted_query.trim_end().to_string()
 }
 
+/// Tokens written on a single line, with the state of the formatter before them to write them
+/// again broken over lines when they don't fit in `max_line_width`
+struct Group<'a> {
+    /// The token starting the group
+    index: usize,
+    /// The token following the group
+    end: usize,
+    /// Where the group is written in the query
+    start: usize,
+    formatter: Formatter<'a>,
+    /// The length of the query before the group, without its trailing whitespace
+    kept: usize,
+    trailing: String,
+}
+
+#[derive(Clone)]
 struct Formatter<'a> {
     index: usize,
     tokens: &'a [Token<'a>],
@@ -190,6 +269,14 @@ struct Formatter<'a> {
     indentation: Indentation<'a>,
     inline_block: InlineBlock,
     block_level: usize,
+    /// The block level of a `SELECT TOP` whose row count is being formatted
+    top_clause: Option<usize>,
+    /// Where the line break written before the last comma starts, when it is a leading comma
+    leading_comma: Option<usize>,
+    /// The tokens starting the groups broken over lines to fit in `max_line_width`
+    breaks: HashSet<usize>,
+    /// Where the last line broken between two tokens to fit in `max_line_width` continues
+    continued_line: Option<usize>,
 }
 
 impl<'a> Formatter<'a> {
@@ -206,7 +293,131 @@ impl<'a> Formatter<'a> {
                 options.max_inline_top_level.unwrap_or(0),
             ),
             block_level: 0,
+            top_clause: None,
+            leading_comma: None,
+            breaks: HashSet::new(),
+            continued_line: None,
+        }
+    }
+
+    /// Where the group of tokens this one starts ends, when they are written on a single line:
+    /// an inline block, or the arguments of a top level keyword kept on a single line
+    fn group_end(&self, token: &Token<'_>) -> Option<usize> {
+        if self.breaks.contains(&self.index) {
+            return None;
+        }
+        let is_clause = match token.kind {
+            TokenKind::OpenParen => {
+                if !self.inline_block.can_begin(self.tokens, self.index) {
+                    return None;
+                }
+                false
+            }
+            TokenKind::ReservedTopLevel | TokenKind::ReservedNewlineAfter
+                if token.alias != "CREATE" =>
+            {
+                let span = self.top_level_tokens_info();
+                let keeps_line = token.kind == TokenKind::ReservedTopLevel
+                    && !span.newline_after
+                    && !self.aligns_clauses();
+                let inlines_arguments = self
+                    .options
+                    .max_inline_arguments
+                    .is_some_and(|limit| limit >= span.full_span);
+                if !keeps_line && !inlines_arguments {
+                    return None;
+                }
+                true
+            }
+            _ => return None,
+        };
+
+        let mut depth = 0;
+        for (index, token) in self.tokens.iter().enumerate().skip(self.index + 1) {
+            match token.kind {
+                TokenKind::OpenParen => depth += 1,
+                TokenKind::CloseParen if depth == 0 => {
+                    return Some(if is_clause { index } else { index + 1 });
+                }
+                TokenKind::CloseParen => depth -= 1,
+                _ if !is_clause || depth > 0 => {}
+                TokenKind::ReservedTopLevel
+                | TokenKind::ReservedTopLevelNoIndent
+                | TokenKind::ReservedNewlineAfter
+                | TokenKind::BlockStart
+                | TokenKind::BlockMiddle
+                | TokenKind::BlockEnd
+                | TokenKind::ReservedStatement => return Some(index),
+                _ if token.alias == ";" => return Some(index),
+                _ => {}
+            }
+        }
+        Some(self.tokens.len())
+    }
+
+    /// Fits the line the token written after `kept` starts on in `width`. The outermost group
+    /// still open on the line is broken first, then the line is broken before the token if a
+    /// space separates it from what comes before, and last the group closed last on the line
+    /// is broken, unless the token is a comment left at the end of the line. Returns the
+    /// position in `groups` of the group to write again broken.
+    fn fit_line(
+        &mut self,
+        query: &mut String,
+        kept: usize,
+        width: usize,
+        groups: &mut Vec<Group<'a>>,
+    ) -> Option<usize> {
+        let start = query.len() - query[kept..].trim_start().len();
+        let line_start = query[..start].rfind('\n').map_or(0, |index| index + 1);
+        groups.retain(|group| group.start >= line_start);
+        let line_end = query[start..]
+            .find('\n')
+            .map_or(query.len(), |index| start + index);
+        if start == query.len() || query[line_start..line_end].trim_end().chars().count() <= width {
+            return None;
+        }
+        if let Some(position) = groups.iter().position(|group| group.end > self.index) {
+            return Some(position);
+        }
+
+        let token = &self.tokens[self.index];
+        if matches!(token.kind, TokenKind::LineComment | TokenKind::BlockComment) {
+            return None;
+        }
+        let separator = &query[kept..start];
+        let after_leading_comma = self.leading_comma.is_some()
+            && self
+                .previous_non_whitespace_token(1)
+                .is_some_and(|previous| previous.alias == ",");
+        let breakable = !separator.is_empty()
+            && !separator.contains('\n')
+            && !query[line_start..kept].trim().is_empty()
+            && !after_leading_comma
+            && !matches!(
+                token.kind,
+                TokenKind::OpenParen
+                    | TokenKind::CloseParen
+                    | TokenKind::ReservedTopLevel
+                    | TokenKind::ReservedTopLevelNoIndent
+                    | TokenKind::ReservedNewlineAfter
+                    | TokenKind::BlockStart
+                    | TokenKind::BlockMiddle
+                    | TokenKind::BlockEnd
+                    | TokenKind::ReservedStatement
+            );
+        if !breakable {
+            return groups.len().checked_sub(1);
         }
+        // A line continued once more keeps the indentation of its continuation
+        let indent = if self.continued_line == Some(line_start) {
+            let line = &query[line_start..];
+            line[..line.len() - line.trim_start().len()].to_string()
+        } else {
+            self.indentation.get_indent(false) + &self.indentation.unit()
+        };
+        query.replace_range(kept..start, &format!("\n{indent}"));
+        self.continued_line = Some(kept + 1);
+        None
     }
 
     fn set_top_level_span(&mut self, token: &'a Token<'a>, span_info: SpanInfo) {
@@ -214,6 +425,9 @@ impl<'a> Formatter<'a> {
     }
 
     fn format_line_comment(&mut self, token: &Token<'_>, query: &mut String) {
+        if self.format_comment_after_leading_comma(token, query) {
+            return;
+        }
         let is_whitespace_followed_by_special_token =
             self.next_token(1).is_some_and(|current_token| {
                 current_token.kind == TokenKind::Whitespace
@@ -238,6 +452,40 @@ impl<'a> Formatter<'a> {
         self.add_new_line(query);
     }
 
+    /// Writes a comment following a leading comma at the end of the previous item, or on its
+    /// own line before the comma when it was on its own line already
+    fn format_comment_after_leading_comma(
+        &mut self,
+        token: &Token<'_>,
+        query: &mut String,
+    ) -> bool {
+        let Some(start) = self.leading_comma.take() else {
+            return false;
+        };
+        if self
+            .previous_non_whitespace_token(1)
+            .is_none_or(|previous| previous.alias != ",")
+        {
+            return false;
+        }
+
+        let comma = query[start..].trim().to_string();
+        query.truncate(start);
+        if self
+            .previous_token(1)
+            .is_some_and(|previous| previous.value.contains('\n'))
+        {
+            self.add_new_line(query);
+        } else {
+            query.push_str("  ");
+        }
+        query.push_str(token.value);
+        self.add_new_line(query);
+        query.push_str(&comma);
+        query.push(' ');
+        true
+    }
+
     fn format_type_specifier(&self, token: &Token<'_>, query: &mut String) {
         const WHITESPACE_BEFORE: &[TokenKind] = &[
             TokenKind::Reserved,
@@ -246,9 +494,11 @@ impl<'a> Formatter<'a> {
         ];
         self.trim_all_spaces_end(query);
         query.push_str(token.value);
-        if self
-            .next_non_whitespace_token(1)
-            .is_some_and(|t| WHITESPACE_BEFORE.contains(&t.kind))
+        // The type following Snowflake's `::` may be a reserved word, like `STRING`
+        if !(self.options.dialect == Dialect::Snowflake && token.value == "::")
+            && self
+                .next_non_whitespace_token(1)
+                .is_some_and(|t| WHITESPACE_BEFORE.contains(&t.kind))
         {
             query.push(' ')
         }
@@ -265,6 +515,9 @@ impl<'a> Formatter<'a> {
         query: &mut String,
     ) -> SpanInfo {
         let span_info = self.top_level_tokens_info();
+        if self.aligns_clauses() {
+            return self.format_aligned_keyword(token, span_info, query);
+        }
 
         if span_info.newline_before {
             self.indentation.decrease_top_level();
@@ -273,7 +526,18 @@ impl<'a> Formatter<'a> {
         query.push_str(&self.equalize_whitespace(&self.format_reserved_word(token.value)));
         if span_info.newline_after && token.alias != "CREATE" {
             self.indentation.increase_top_level(span_info.clone());
-            self.add_new_line(query);
+            // `SELECT TOP (10)` keeps its row count on the line of the keyword
+            if token
+                .value
+                .split_whitespace()
+                .next_back()
+                .is_some_and(|word| word.eq_ignore_ascii_case("TOP"))
+            {
+                self.top_clause = Some(self.block_level);
+                query.push(' ');
+            } else {
+                self.add_new_line(query);
+            }
         } else {
             query.push(' ');
         }
@@ -308,12 +572,17 @@ impl<'a> Formatter<'a> {
         query: &mut String,
     ) -> SpanInfo {
         let span_info = self.top_level_tokens_info();
+        if self.aligns_clauses() {
+            return self.format_aligned_keyword(token, span_info, query);
+        }
 
         let newline_before = match (
             self.options.max_inline_top_level,
             self.indentation.previous_top_level_reserved(),
         ) {
-            (Some(limit), Some((_, span))) => limit < span.full_span + token.value.len(),
+            (Some(limit), Some((_, span))) => {
+                limit < span.full_span.saturating_add(token.value.len())
+            }
             _ => true,
         };
 
@@ -333,6 +602,113 @@ impl<'a> Formatter<'a> {
         span_info
     }
 
+    /// Writes a top level keyword followed by its arguments on the same line, right-aligned on
+    /// the river of its block in the river layout
+    fn format_aligned_keyword(
+        &mut self,
+        token: &Token<'_>,
+        span_info: SpanInfo,
+        query: &mut String,
+    ) -> SpanInfo {
+        let keyword = self.equalize_whitespace(&self.format_reserved_word(token.value));
+        let river = match self.options.clause_layout {
+            ClauseLayout::River => self.river(),
+            _ => keyword.chars().count(),
+        };
+        self.indentation.decrease_top_level();
+        self.add_new_line(query);
+        query.push_str(&" ".repeat(river.saturating_sub(keyword.chars().count())));
+        query.push_str(&keyword);
+        query.push(' ');
+
+        let span_info = SpanInfo {
+            newline_after: true,
+            ..span_info
+        };
+        if token.alias != "CREATE" {
+            self.indentation
+                .increase_aligned_top_level(span_info.clone(), river);
+        }
+        span_info
+    }
+
+    /// Starts the line of an `AND`, `OR` or join, right-aligned on the river of the keyword
+    /// it continues when the keywords are aligned
+    fn add_river_line(&self, token: &Token<'_>, query: &mut String) {
+        let indent = if self.aligns_clauses() && self.is_river_keyword(token) {
+            let width = self.equalize_whitespace(token.value).chars().count();
+            self.indentation.get_river_indent(width)
+        } else {
+            None
+        };
+        let Some(indent) = indent else {
+            self.add_new_line(query);
+            return;
+        };
+        self.trim_spaces_end(query);
+        if !query.ends_with('\n') {
+            query.push('\n');
+        }
+        query.push_str(&indent);
+    }
+
+    /// Whether the keywords are aligned with their arguments at this point of the query
+    fn aligns_clauses(&self) -> bool {
+        self.options.clause_layout != ClauseLayout::Indented
+            && !self.options.inline
+            && !self.inline_block.is_active()
+    }
+
+    fn is_river_keyword(&self, token: &Token<'_>) -> bool {
+        match token.kind {
+            TokenKind::ReservedTopLevel | TokenKind::ReservedNewlineAfter => true,
+            TokenKind::ReservedNewline => {
+                token
+                    .value
+                    .split_whitespace()
+                    .next_back()
+                    .is_some_and(|word| {
+                        ["AND", "OR", "XOR", "JOIN", "APPLY"]
+                            .iter()
+                            .any(|keyword| word.eq_ignore_ascii_case(keyword))
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    /// The width of the keywords right-aligned in the current statement or block, which is
+    /// the one of the widest of them
+    fn river(&mut self) -> usize {
+        if let Some(river) = self.indentation.river() {
+            return river;
+        }
+        let mut depth = 0;
+        let mut river = 0;
+        for token in &self.tokens[self.index..] {
+            match token.kind {
+                TokenKind::OpenParen => depth += 1,
+                TokenKind::CloseParen if depth == 0 => break,
+                TokenKind::CloseParen => depth -= 1,
+                TokenKind::BlockStart
+                | TokenKind::BlockMiddle
+                | TokenKind::BlockEnd
+                | TokenKind::ReservedStatement
+                    if depth == 0 =>
+                {
+                    break;
+                }
+                _ if depth == 0 && token.alias == ";" => break,
+                _ if depth == 0 && self.is_river_keyword(token) => {
+                    river = river.max(self.equalize_whitespace(token.value).chars().count());
+                }
+                _ => {}
+            }
+        }
+        self.indentation.set_river(river);
+        river
+    }
+
     fn format_newline_reserved_word(&mut self, token: &Token<'_>, query: &mut String) {
         // line comments force a newline
         let after_line_comment = self
@@ -358,7 +734,7 @@ impl<'a> Formatter<'a> {
                 self.trim_spaces_end(query);
             }
 
-            self.add_new_line(query);
+            self.add_river_line(token, query);
         } else {
             self.trim_spaces_end(query);
             query.push(' ');
@@ -378,6 +754,92 @@ impl<'a> Formatter<'a> {
         };
     }
 
+    /// Whether the token is the first one after the row count of a `SELECT TOP`
+    fn ends_top_clause(&mut self, token: &Token<'_>) -> bool {
+        if self.top_clause != Some(self.block_level) {
+            return false;
+        }
+        match token.kind {
+            TokenKind::Whitespace
+            | TokenKind::LineComment
+            | TokenKind::BlockComment
+            | TokenKind::OpenParen
+            | TokenKind::Number => false,
+            TokenKind::Reserved
+                if ["PERCENT", "WITH TIES"].iter().any(|word| {
+                    self.equalize_whitespace(token.value)
+                        .eq_ignore_ascii_case(word)
+                }) =>
+            {
+                false
+            }
+            _ => {
+                self.top_clause = None;
+                // The separator of a statement without columns trims the line itself
+                token.alias != ";"
+            }
+        }
+    }
+
+    // Procedural blocks indent their statements, `BEGIN` and `DECLARE` start a new line while
+    // `THEN`, `LOOP` or `IS` end the line they are on
+    fn format_block_start(&mut self, token: &'a Token<'a>, query: &mut String) {
+        self.indentation.reset_indentation();
+        let first_word = token.value.split_whitespace().next().unwrap_or_default();
+        if ["BEGIN", "DECLARE"]
+            .iter()
+            .any(|word| first_word.eq_ignore_ascii_case(word))
+        {
+            self.add_new_line(query);
+        }
+        query.push_str(&self.equalize_whitespace(&self.format_reserved_word(token.value)));
+        self.indentation.open_block(token);
+        self.add_new_line(query);
+    }
+
+    fn format_block_middle(&mut self, token: &'a Token<'a>, query: &mut String) {
+        self.indentation.reset_indentation();
+        self.indentation.close_block(token);
+        self.add_new_line(query);
+        query.push_str(&self.equalize_whitespace(&self.format_reserved_word(token.value)));
+        self.indentation.open_block(token);
+        self.add_new_line(query);
+    }
+
+    fn format_block_end(&mut self, token: &Token<'_>, query: &mut String) {
+        self.indentation.reset_indentation();
+        self.indentation.close_block(token);
+        self.add_new_line(query);
+        query.push_str(&self.equalize_whitespace(&self.format_reserved_word(token.value)));
+        // T-SQL statements don't need a `;`, so one can follow an `END` directly
+        if self.options.dialect == Dialect::SQLServer
+            && self.next_non_whitespace_token(1).is_some_and(|next| {
+                next.alias != ";" && !matches!(next.kind, TokenKind::LineComment)
+            })
+        {
+            self.add_new_line(query);
+        } else {
+            query.push(' ');
+        }
+    }
+
+    // T-SQL statements don't need a `;`, so the keywords starting them start a new line, but
+    // for the statement of an `ELSE`
+    fn format_statement_start(&mut self, token: &Token<'_>, query: &mut String) {
+        self.indentation.reset_indentation();
+        if self
+            .previous_non_whitespace_token(1)
+            .is_none_or(|previous| {
+                previous.kind != TokenKind::ReservedStatement
+                    || !previous.value.eq_ignore_ascii_case("ELSE")
+            })
+        {
+            self.add_new_line(query);
+        }
+        query.push_str(&self.equalize_whitespace(&self.format_reserved_word(token.value)));
+        query.push(' ');
+    }
+
     // Opening parentheses increase the block indent level and start a new line
     fn format_opening_parentheses(&mut self, token: &Token<'_>, query: &mut String) {
         self.block_level += 1;
@@ -387,10 +849,15 @@ impl<'a> Formatter<'a> {
             TokenKind::LineComment,
         ];
 
-        const ADD_WHITESPACE_BETWEEN: &[TokenKind] = &[TokenKind::CloseParen, TokenKind::Reserved];
+        const ADD_WHITESPACE_BETWEEN: &[TokenKind] = &[
+            TokenKind::CloseParen,
+            TokenKind::Reserved,
+            TokenKind::ReservedStatement,
+        ];
         const BEFORE_ARRAY: &[TokenKind] =
             &[TokenKind::CloseParen, TokenKind::Word, TokenKind::Reserved];
-        let inlined = self.inline_block.begin_if_possible(self.tokens, self.index);
+        let inlined = !self.breaks.contains(&self.index)
+            && self.inline_block.begin_if_possible(self.tokens, self.index);
         let previous_non_whitespace_token = self.previous_non_whitespace_token(1);
         let fold_in_top_level = !inlined
             && self.options.max_inline_top_level.is_some()
@@ -511,29 +978,76 @@ impl<'a> Formatter<'a> {
     // Commas start a new line (unless within inline parentheses or SQL "LIMIT" clause)
     fn format_comma(&mut self, token: &Token<'_>, query: &mut String) {
         self.trim_spaces_end(query);
+        let new_line = self.breaks_after_comma();
+        let leading = self.options.comma_position == CommaPosition::Leading
+            && !self.options.inline
+            && !self.ends_list();
+        self.leading_comma = (new_line && leading).then_some(query.len());
+
+        if self.leading_comma.is_some() {
+            self.add_new_line(query);
+        }
         query.push_str(token.value);
         query.push(' ');
+        if new_line && !leading {
+            self.add_new_line(query);
+        }
+    }
 
+    /// Whether the comma is a trailing comma, which stays on the line of the last item of its
+    /// list
+    fn ends_list(&self) -> bool {
+        self.tokens[self.index + 1..]
+            .iter()
+            .find(|t| {
+                !matches!(
+                    t.kind,
+                    TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment
+                )
+            })
+            .is_none_or(|next| {
+                next.alias == ";"
+                    || matches!(
+                        next.kind,
+                        TokenKind::CloseParen
+                            | TokenKind::ReservedTopLevel
+                            | TokenKind::ReservedTopLevelNoIndent
+                            | TokenKind::ReservedNewlineAfter
+                    )
+            })
+    }
+
+    /// Whether the list a comma belongs to is broken over lines
+    fn breaks_after_comma(&self) -> bool {
         if self.inline_block.is_active() {
-            return;
+            return false;
         }
         if self
             .indentation
             .previous_reserved()
-            .map(|word| word.value.to_lowercase() == "limit")
+            .map(|word| {
+                let word = word.value.to_lowercase();
+                let dialect = self.options.dialect;
+                // `LIMIT 5, 10`
+                word == "limit"
+                    // SQLite's `WITHOUT ROWID, STRICT` table options
+                    || dialect == Dialect::Sqlite && (word == "strict" || word.ends_with("rowid"))
+                    // The arguments of PL/pgSQL's `RAISE NOTICE 'format', ...`
+                    || dialect == Dialect::PostgreSql && word == "raise"
+            })
             .unwrap_or(false)
         {
-            return;
+            return false;
         }
 
         if let Some((_, span)) = self.indentation.previous_top_level_reserved() {
             let limit = self.options.max_inline_arguments.unwrap_or(0);
             if limit >= span.full_span {
-                return;
+                return false;
             }
         }
 
-        self.add_new_line(query);
+        true
     }
 
     fn format_with_space_after(&self, token: &Token<'_>, query: &mut String) {
@@ -547,13 +1061,89 @@ impl<'a> Formatter<'a> {
         query.push_str(token.value);
     }
 
+    /// Whether the token is the dollar-quoted body of a PostgreSQL function or `DO` block
+    /// written in PL/pgSQL or SQL, and those should be formatted
+    fn is_function_body(&self, token: &Token<'_>) -> bool {
+        if !self.options.format_function_bodies
+            || self.options.dialect != Dialect::PostgreSql
+            || !token.value.starts_with('$')
+        {
+            return false;
+        }
+        let start = self.tokens[..self.index]
+            .iter()
+            .rposition(|t| t.alias == ";")
+            .map_or(0, |index| index + 1);
+        let end = self.tokens[self.index..]
+            .iter()
+            .position(|t| t.alias == ";")
+            .map_or(self.tokens.len(), |index| self.index + index);
+        let mut statement = self.tokens[start..end].iter().filter(|t| {
+            !matches!(
+                t.kind,
+                TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment
+            )
+        });
+        // `DO` blocks are written in PL/pgSQL unless they say otherwise
+        let is_do = statement
+            .clone()
+            .next()
+            .is_some_and(|t| t.value.eq_ignore_ascii_case("DO"));
+        let language = statement
+            .find(|t| t.value.eq_ignore_ascii_case("LANGUAGE"))
+            .and_then(|_| statement.next())
+            .map(|t| t.value.trim_matches(['\'', '"']));
+        match language {
+            Some(language) => ["plpgsql", "sql"]
+                .iter()
+                .any(|name| language.eq_ignore_ascii_case(name)),
+            None => is_do,
+        }
+    }
+
+    /// Formats a function body on its own, between lines holding its dollar quotes
+    fn format_function_body(&mut self, token: &Token<'_>, query: &mut String) {
+        let tag_len = token.value[1..].find('$').map_or(1, |end| end + 2);
+        let (quote, rest) = token.value.split_at(tag_len);
+        let body = &rest[..rest.len() - tag_len];
+        let tokens = tokenize_function_body(body, self.options);
+        let formatted = format(&tokens, &QueryParams::None, self.options);
+
+        let indent = self.indentation.get_indent(false);
+        query.push_str(quote);
+        for line in formatted.lines() {
+            if self.options.inline {
+                query.push(' ');
+            } else {
+                query.push('\n');
+                if !line.is_empty() {
+                    query.push_str(&indent);
+                }
+            }
+            query.push_str(line);
+        }
+        self.add_new_line(query);
+        query.push_str(quote);
+        query.push(' ');
+    }
+
     fn format_query_separator(&mut self, token: &Token<'_>, query: &mut String) {
         self.indentation.reset_indentation();
+        // Unlike `;`, Oracle's `/` and MySQL's custom delimiters end the whole block
+        if token.value != ";" {
+            self.indentation.close_all_blocks();
+        }
         self.trim_spaces_end(query);
+        // SQL*Plus only recognizes a `/` alone on its line, and a custom delimiter like `//`
+        // is kept apart from the statement it ends
+        if !token.value.starts_with(';') && !query.is_empty() && !query.ends_with('\n') {
+            query.push(if token.value == "/" { '\n' } else { ' ' });
+        }
         query.push_str(token.value);
         for _ in 0..self.options.lines_between_queries {
             query.push('\n');
         }
+        query.push_str(&self.indentation.get_indent(false));
     }
 
     fn add_new_line_inner(&self, query: &mut String, folded: bool) {
@@ -700,7 +1290,11 @@ impl<'a> Formatter<'a> {
                 }
                 TokenKind::ReservedTopLevel
                 | TokenKind::ReservedTopLevelNoIndent
-                | TokenKind::ReservedNewlineAfter => {
+                | TokenKind::ReservedNewlineAfter
+                | TokenKind::BlockStart
+                | TokenKind::BlockMiddle
+                | TokenKind::BlockEnd
+                | TokenKind::ReservedStatement => {
                     if block_level == self.block_level {
                         break;
                     }
@@ -709,7 +1303,7 @@ impl<'a> Formatter<'a> {
                     full_span += 1;
                     continue;
                 }
-                TokenKind::Operator if token.value == ";" => {
+                TokenKind::Operator if token.alias == ";" => {
                     if block_level == self.block_level {
                         break;
                     }
@@ -730,6 +1324,12 @@ impl<'a> Formatter<'a> {
             full_span += token.value.len();
         }
 
+        // The arguments of a clause that didn't fit in `max_line_width` are broken over lines
+        // like the ones too long to be inlined
+        if self.breaks.contains(&self.index) {
+            full_span = usize::MAX;
+        }
+
         let limit = self.options.max_inline_top_level.unwrap_or(0);
         // if we are inside an inline block we decide our behaviour as if were inline
         let block_len = self.inline_block.cur_len();
@@ -1,21 +1,30 @@
 use crate::{FormatOptions, Indent, SpanInfo, tokenizer::Token};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 struct PreviousTokens<'a> {
     top_level_reserved: Option<(&'a Token<'a>, SpanInfo)>,
     reserved: Option<&'a Token<'a>>,
 }
 
+#[derive(Clone)]
 pub(crate) struct Indentation<'a> {
     options: &'a FormatOptions<'a>,
     indent_types: Vec<IndentType>,
     top_level_span: Vec<SpanInfo>,
     previous: Vec<PreviousTokens<'a>>,
+    /// The tokens opening the procedural blocks, which outlive the statements they contain
+    blocks: Vec<&'a Token<'a>>,
+    /// The width of the keywords right-aligned in the statement and in each block opened in
+    /// it, once known
+    rivers: Vec<Option<usize>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum IndentType {
-    Top,
+    /// The arguments of a top level keyword, written after the river of that width when they
+    /// are aligned on the keyword's line: the width of the widest keyword of the block when
+    /// the keywords are right-aligned, or of the keyword itself
+    Top(Option<usize>),
     Block,
     FoldedBlock,
 }
@@ -27,26 +36,68 @@ impl<'a> Indentation<'a> {
             indent_types: Vec::new(),
             top_level_span: Vec::new(),
             previous: Vec::new(),
+            blocks: Vec::new(),
+            rivers: Vec::new(),
         }
     }
 
     pub fn get_indent(&self, folded: bool) -> String {
-        // TODO compute in place?
-        let level = self
-            .indent_types
-            .iter()
-            .copied()
-            .filter(|t| *t != IndentType::FoldedBlock)
-            .count()
-            - if folded { 1 } else { 0 };
+        self.indent_of(&self.indent_types, folded)
+    }
+
+    /// The indentation right-aligning a keyword of this width on the river of the top level
+    /// keyword it continues, if the keywords are right-aligned
+    pub fn get_river_indent(&self, width: usize) -> Option<String> {
+        let Some((IndentType::Top(Some(river)), outer)) = self.indent_types.split_last() else {
+            return None;
+        };
+        let mut indent = self.indent_of(outer, false);
+        indent.push_str(&" ".repeat(river.saturating_sub(width)));
+        Some(indent)
+    }
+
+    /// A single level of indentation
+    pub fn unit(&self) -> String {
         match self.options.indent {
-            Indent::Spaces(num_spaces) => " ".repeat(num_spaces as usize).repeat(level),
-            Indent::Tabs => "\t".repeat(level),
+            Indent::Spaces(num_spaces) => " ".repeat(num_spaces as usize),
+            Indent::Tabs => "\t".to_string(),
         }
     }
 
+    fn indent_of(&self, indent_types: &[IndentType], folded: bool) -> String {
+        let unit = self.unit();
+        // A folded block closes one level less indented
+        let skipped = indent_types
+            .iter()
+            .rposition(|t| *t != IndentType::FoldedBlock)
+            .filter(|_| folded);
+        let blocks = if folded && skipped.is_none() {
+            self.blocks.len().saturating_sub(1)
+        } else {
+            self.blocks.len()
+        };
+
+        let mut indent = unit.repeat(blocks);
+        for (i, indent_type) in indent_types.iter().enumerate() {
+            match indent_type {
+                _ if Some(i) == skipped => {}
+                IndentType::Top(Some(river)) => indent.push_str(&" ".repeat(river + 1)),
+                IndentType::Top(None) | IndentType::Block => indent.push_str(&unit),
+                IndentType::FoldedBlock => {}
+            }
+        }
+        indent
+    }
+
     pub fn increase_top_level(&mut self, span: SpanInfo) {
-        self.indent_types.push(IndentType::Top);
+        self.indent_types.push(IndentType::Top(None));
+        self.top_level_span.push(span);
+    }
+
+    /// Aligns the following lines on the first argument of a top level keyword, written after
+    /// a river of this width
+    pub fn increase_aligned_top_level(&mut self, span: SpanInfo, river: usize) {
+        self.indent_types.push(IndentType::Top(Some(river)));
         self.top_level_span.push(span);
     }
 
@@ -57,10 +108,11 @@ impl<'a> Indentation<'a> {
             IndentType::Block
         });
         self.previous.push(Default::default());
+        self.rivers.push(None);
     }
 
     pub fn decrease_top_level(&mut self) {
-        if self.indent_types.last() == Some(&IndentType::Top) {
+        if matches!(self.indent_types.last(), Some(IndentType::Top(_))) {
             self.indent_types.pop();
             self.top_level_span.pop();
             self.previous.pop();
@@ -74,7 +126,8 @@ impl<'a> Indentation<'a> {
             let kind = self.indent_types.pop();
             self.previous.pop();
             folded = kind == Some(IndentType::FoldedBlock);
-            if kind != Some(IndentType::Top) {
+            if !matches!(kind, Some(IndentType::Top(_))) {
+                self.rivers.pop();
                 break;
             } else {
                 self.top_level_span.pop();
@@ -87,6 +140,42 @@ impl<'a> Indentation<'a> {
         self.indent_types.clear();
         self.top_level_span.clear();
         self.previous.clear();
+        self.rivers.clear();
+    }
+
+    /// The width of the keywords right-aligned in the current block, once known
+    pub fn river(&self) -> Option<usize> {
+        self.rivers.last().copied().flatten()
+    }
+
+    pub fn set_river(&mut self, width: usize) {
+        if let Some(river) = self.rivers.last_mut() {
+            *river = Some(width);
+        } else {
+            self.rivers.push(Some(width));
+        }
+    }
+
+    pub fn open_block(&mut self, token: &'a Token<'a>) {
+        self.blocks.push(token);
+    }
+
+    pub fn close_block(&mut self, token: &Token<'_>) {
+        // An `END` also closes the exception handlers of its block
+        if token.value.eq_ignore_ascii_case("END") {
+            while self
+                .blocks
+                .last()
+                .is_some_and(|block| block.value.eq_ignore_ascii_case("THEN"))
+            {
+                self.blocks.pop();
+            }
+        }
+        self.blocks.pop();
+    }
+
+    pub fn close_all_blocks(&mut self) {
+        self.blocks.clear();
     }
 
     pub fn set_previous_reserved(&mut self, token: &'a Token<'a>) {
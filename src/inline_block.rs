@@ -1,5 +1,6 @@
 use crate::tokenizer::{Token, TokenKind};
 
+#[derive(Clone)]
 pub(crate) struct BlockInfo {
     length: usize,
     has_forbidden_tokens: bool,
@@ -7,6 +8,7 @@ pub(crate) struct BlockInfo {
     top_level_token_span: usize,
 }
 
+#[derive(Clone)]
 pub(crate) struct InlineBlock {
     level: usize,
     inline_max_length: usize,
@@ -61,6 +63,12 @@ impl InlineBlock {
         }
     }
 
+    /// Whether a block starting at `index` would be inlined on its own, outside of any
+    /// inline block
+    pub fn can_begin(&self, tokens: &[Token<'_>], index: usize) -> bool {
+        self.level == 0 && self.is_inline_block(&self.build_info(tokens, index))
+    }
+
     pub fn end(&mut self) {
         self.info.pop();
         self.level -= 1;
@@ -130,6 +138,6 @@ impl InlineBlock {
     fn is_forbidden_token(&self, token: &Token<'_>) -> bool {
         token.kind == TokenKind::LineComment
             || token.kind == TokenKind::BlockComment
-            || token.value == ";"
+            || token.alias == ";"
     }
 }
@@ -9,15 +9,26 @@
 // This lint is overly pedantic and annoying
 #![allow(clippy::needless_lifetimes)]
 
+mod config;
+mod diagnostics;
 mod formatter;
 mod indentation;
 mod inline_block;
 mod params;
+mod range;
 mod tokenizer;
 
 #[cfg(feature = "debug")]
 mod debug;
 
+pub use config::{Config, ConfigError};
+pub use diagnostics::{Diagnostic, DiagnosticKind, FormatError};
+pub use range::TextEdit;
+
+use std::fmt;
+use std::ops::{Range, RangeInclusive};
+use std::str::FromStr;
+
 /// Formats whitespace in a SQL string to make it easier to read.
 /// Optionally replaces parameter placeholders with `params`.
 pub fn format(query: &str, params: &QueryParams, options: &FormatOptions) -> String {
@@ -27,19 +38,223 @@ pub fn format(query: &str, params: &QueryParams, options: &FormatOptions) -> Str
     formatter::format(&tokens, params, options)
 }
 
+/// Formats a SQL string like [`format`], but fails instead of guessing when the query is
+/// malformed.
+///
+/// Unterminated strings and block comments, closing parentheses, brackets or `END`s without
+/// a matching opening token and blocks that are never closed are all reported, each with
+/// its location in `query`.
+pub fn try_format(
+    query: &str,
+    params: &QueryParams,
+    options: &FormatOptions,
+) -> Result<String, FormatError> {
+    let named_placeholders = matches!(params, QueryParams::Named(_));
+
+    let tokens = tokenizer::tokenize(query, named_placeholders, options);
+    let diagnostics = diagnostics::check(&tokens, query.len(), options.dialect);
+    if !diagnostics.is_empty() {
+        return Err(FormatError { diagnostics });
+    }
+    Ok(formatter::format(&tokens, params, options))
+}
+
+/// Formats only the statements of `document` that overlap the byte `range`, e.g. to format
+/// a selection in an editor. An empty range formats the statement the position touches.
+///
+/// Returns one edit per statement whose formatting changed; the rest of the document is
+/// left untouched. Lines after the first of a formatted statement are indented like the
+/// line the statement starts on. Each statement is formatted on its own, so indexed
+/// `params` are numbered from the start of the statement. Use [`TextEdit::apply`] to get
+/// the edited document.
+pub fn format_range(
+    document: &str,
+    range: Range<usize>,
+    params: &QueryParams,
+    options: &FormatOptions,
+) -> Vec<TextEdit> {
+    range::format_range(document, range, params, options)
+}
+
+/// Formats only the statements of `document` that overlap the given 1-based lines, like
+/// [`format_range`].
+pub fn format_lines(
+    document: &str,
+    lines: RangeInclusive<usize>,
+    params: &QueryParams,
+    options: &FormatOptions,
+) -> Vec<TextEdit> {
+    let range = range::line_range(document, lines);
+    range::format_range(document, range, params, options)
+}
+
+/// Splits a SQL string into the tokens used by the formatter.
+///
+/// Only `dialect` and `joins_as_top_level` from `options` affect the result. The tokens
+/// cover the whole query, whitespace included, so concatenating their text gives back the
+/// original query.
+pub fn tokenize<'a>(query: &'a str, options: &FormatOptions) -> Vec<Token<'a>> {
+    with_positions(&tokenizer::tokenize(query, false, options))
+}
+
+fn with_positions<'a>(tokens: &[tokenizer::Token<'a>]) -> Vec<Token<'a>> {
+    let mut offset = 0;
+    let mut line = 1;
+    let mut column = 1;
+
+    tokens
+        .iter()
+        .map(|token| {
+            let start = offset;
+            let (start_line, start_column) = (line, column);
+            offset += token.value.len();
+            for c in token.value.chars() {
+                if c == '\n' {
+                    line += 1;
+                    column = 1;
+                } else {
+                    column += 1;
+                }
+            }
+            Token {
+                kind: token.kind.into(),
+                text: token.value,
+                span: start..offset,
+                line: start_line,
+                column: start_column,
+            }
+        })
+        .collect()
+}
+
 /// The SQL dialect to use. This affects parsing of special characters.
+///
+/// With the `serde` feature a dialect is (de)serialized as its lowercase name, e.g.
+/// `"postgresql"`, the same names accepted by [`Dialect::from_str`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Dialect {
     /// Generic SQL syntax, most dialect-specific constructs are disabled
     Generic,
-    /// Enables array syntax (`[`, `]`) and operators
+    /// Enables array syntax (`[`, `]`) and operators, `#` is an operator rather than a comment,
+    /// dollar-quoted strings are accepted and the rows of `COPY ... FROM STDIN` are kept as is
+    #[cfg_attr(feature = "serde", serde(alias = "postgres"))]
     PostgreSql,
-    /// Enables `[bracketed identifiers]` and `@variables`
+    /// T-SQL: enables `[bracketed identifiers]`, `@variables` and `#temp` tables, and indents
+    /// `BEGIN ... END` and `TRY`/`CATCH` blocks
+    #[cfg_attr(feature = "serde", serde(alias = "mssql"))]
     SQLServer,
+    /// MySQL and MariaDB: `--` only starts a comment when followed by whitespace, a backslash
+    /// escapes any character in strings and `DELIMITER` changes the statement delimiter
+    #[cfg_attr(feature = "serde", serde(alias = "mariadb"))]
+    MySql,
+    /// SQLite: `?NNN` placeholders are one-indexed, `[bracketed identifiers]` are accepted and
+    /// only SQLite's own keywords are reserved, so `key` or `replace` can name a column
+    #[cfg_attr(feature = "serde", serde(alias = "sqlite3"))]
+    Sqlite,
+    /// Oracle: PL/SQL blocks are indented, `q'[...]'` strings are accepted, a backslash doesn't
+    /// escape in strings and a `/` on its own line ends a block
+    #[cfg_attr(feature = "serde", serde(alias = "plsql"))]
+    Oracle,
+    /// BigQuery: `STRUCT<...>` and `ARRAY<...>` types, raw, bytes and triple-quoted strings,
+    /// `@@` system variables and the blocks of scripts are recognized
+    BigQuery,
+    /// Snowflake: `$$` bodies are strings, `@` names a stage rather than a placeholder and
+    /// `:` accesses the fields of semi-structured values
+    Snowflake,
+    /// ClickHouse: the clauses of table engines are laid out like those of queries and
+    /// `{name:Type}` placeholders are named after the part before the `:`
+    ClickHouse,
+    /// DuckDB: `{...}` struct literals are formatted like parentheses and `PIVOT` statements
+    /// like queries
+    DuckDb,
+    /// Spark SQL, Databricks and Hive: the storage clauses of `CREATE TABLE` start lines
+    Spark,
+    /// Trino, Presto and Athena
+    Trino,
+    /// Amazon Redshift: table attributes and the options of `COPY` and `UNLOAD` start lines
+    Redshift,
 }
 
+/// The names accepted when parsing a [`Dialect`], the first one for each dialect is canonical
+const DIALECT_NAMES: &[(&str, Dialect)] = &[
+    ("generic", Dialect::Generic),
+    ("postgresql", Dialect::PostgreSql),
+    ("postgres", Dialect::PostgreSql),
+    ("sqlserver", Dialect::SQLServer),
+    ("mssql", Dialect::SQLServer),
+    ("mysql", Dialect::MySql),
+    ("mariadb", Dialect::MySql),
+    ("sqlite", Dialect::Sqlite),
+    ("sqlite3", Dialect::Sqlite),
+    ("oracle", Dialect::Oracle),
+    ("plsql", Dialect::Oracle),
+    ("bigquery", Dialect::BigQuery),
+    ("snowflake", Dialect::Snowflake),
+    ("clickhouse", Dialect::ClickHouse),
+    ("duckdb", Dialect::DuckDb),
+    ("spark", Dialect::Spark),
+    ("databricks", Dialect::Spark),
+    ("hive", Dialect::Spark),
+    ("trino", Dialect::Trino),
+    ("presto", Dialect::Trino),
+    ("athena", Dialect::Trino),
+    ("redshift", Dialect::Redshift),
+];
+
+impl FromStr for Dialect {
+    type Err = ParseDialectError;
+
+    /// Parses a dialect name such as `postgresql`, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DIALECT_NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|&(_, dialect)| dialect)
+            .ok_or(ParseDialectError)
+    }
+}
+
+/// The error returned when parsing an unknown [`Dialect`] name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDialectError;
+
+impl fmt::Display for ParseDialectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("expected one of ")?;
+        let mut previous = None;
+        for (i, (name, dialect)) in DIALECT_NAMES.iter().enumerate() {
+            // Aliases are accepted but not advertised
+            if previous == Some(dialect) {
+                continue;
+            }
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(name)?;
+            previous = Some(dialect);
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseDialectError {}
+
 /// Options for controlling how the library formats SQL
+///
+/// With the `serde` feature the options are (de)serialized as a map using the field names
+/// as keys, missing keys keep their default value. Since `ignore_case_convert` borrows from
+/// the input, use [`Config`] to deserialize from a reader or a format with escaped strings.
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
 pub struct FormatOptions<'a> {
     /// Controls the type and length of indentation to use
     ///
@@ -56,6 +271,7 @@ pub struct FormatOptions<'a> {
     /// Ignore case conversion for specified strings in the array.
     ///
     /// Default: None
+    #[cfg_attr(feature = "serde", serde(borrow))]
     pub ignore_case_convert: Option<Vec<&'a str>>,
     /// Keep the query in a single line
     ///
@@ -75,6 +291,23 @@ pub struct FormatOptions<'a> {
     ///
     /// Default: None
     pub max_inline_top_level: Option<usize>,
+    /// Break lines longer than this, outermost block first, wherever there is a space or a
+    /// line break between two tokens
+    ///
+    /// The limits above still decide what is kept on a single line when it fits, and nothing
+    /// is broken when `inline` is set.
+    ///
+    /// Default: None
+    pub max_line_width: Option<usize>,
+    /// Where to write the commas of lists broken over lines, such as select lists, values or
+    /// function arguments
+    ///
+    /// Default: Trailing
+    pub comma_position: CommaPosition,
+    /// How the arguments of top level keywords such as `SELECT` or `WHERE` are laid out
+    ///
+    /// Default: Indented
+    pub clause_layout: ClauseLayout,
     /// Consider any JOIN statement as a top level keyword instead of a reserved keyword
     ///
     /// Default: false,
@@ -83,6 +316,11 @@ pub struct FormatOptions<'a> {
     ///
     /// Default: Generic
     pub dialect: Dialect,
+    /// Format the dollar-quoted bodies of PostgreSQL functions and `DO` blocks written in
+    /// PL/pgSQL or SQL, instead of keeping them as they are
+    ///
+    /// Default: true
+    pub format_function_bodies: bool,
 }
 
 impl<'a> Default for FormatOptions<'a> {
@@ -96,19 +334,114 @@ impl<'a> Default for FormatOptions<'a> {
             max_inline_block: 50,
             max_inline_arguments: None,
             max_inline_top_level: None,
+            max_line_width: None,
+            comma_position: CommaPosition::Trailing,
+            clause_layout: ClauseLayout::Indented,
             joins_as_top_level: false,
             dialect: Dialect::Generic,
+            format_function_bodies: true,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Where the commas separating the items of a list broken over lines are written
+///
+/// With the `serde` feature a position is (de)serialized as its lowercase name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum CommaPosition {
+    /// At the end of each line but the last
+    Trailing,
+    /// At the start of each line but the first, before the item
+    Leading,
+}
+
+/// How the arguments of top level keywords are laid out
+///
+/// With the `serde` feature a layout is (de)serialized as its lowercase name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ClauseLayout {
+    /// The keyword on its own line, followed by its arguments indented on the next lines
+    Indented,
+    /// The keywords of each statement and subquery right-aligned, along with `AND`, `OR` and
+    /// joins, so that their arguments start in a single column after them
+    River,
+    /// The first argument on the line of its keyword and the next ones aligned under it,
+    /// with `AND`, `OR` and joins right-aligned on the keyword they continue
+    Compact,
+}
+
+/// With the `serde` feature an indent is (de)serialized as the number of spaces, or as the
+/// string `"tabs"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Indent {
     Spaces(u8),
     Tabs,
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Indent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Indent::Spaces(spaces) => serializer.serialize_u8(*spaces),
+            Indent::Tabs => serializer.serialize_str("tabs"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Indent {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IndentVisitor;
+
+        impl serde::de::Visitor<'_> for IndentVisitor {
+            type Value = Indent;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number of spaces or \"tabs\"")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Indent, E> {
+                u8::try_from(v)
+                    .map(Indent::Spaces)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Indent, E> {
+                u8::try_from(v)
+                    .map(Indent::Spaces)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Indent, E> {
+                match v {
+                    "tabs" => Ok(Indent::Tabs),
+                    _ => Err(E::invalid_value(serde::de::Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(IndentVisitor)
+    }
+}
+
+/// With the `serde` feature the parameters are (de)serialized as `{"named": [["name",
+/// "value"], ...]}`, `{"indexed": ["value", ...]}` or `"none"`.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum QueryParams {
     Named(Vec<(String, String)>),
     Indexed(Vec<String>),
@@ -116,6 +449,91 @@ pub enum QueryParams {
     None,
 }
 
+/// A token of a SQL query, as returned by [`tokenize`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    /// The original text of the token
+    pub text: &'a str,
+    /// The byte range of the token in the query
+    pub span: Range<usize>,
+    /// The 1-based line the token starts on
+    pub line: usize,
+    /// The 1-based column the token starts at, counted in characters
+    pub column: usize,
+}
+
+/// The kind of a [`Token`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TokenKind {
+    /// Spaces, tabs and line breaks
+    Whitespace,
+    /// A `--` or `#` comment running until the end of the line
+    LineComment,
+    /// A `/* ... */` comment
+    BlockComment,
+    /// A quoted string or identifier, such as `'text'`, `"column"` or `` `table` ``
+    String,
+    Number,
+    /// An identifier or any other word that isn't a reserved keyword
+    Word,
+    Operator,
+    /// A query parameter placeholder, such as `?`, `$1` or `:name`
+    Placeholder,
+    /// A type cast `::` or an array type suffix `[]`
+    TypeSpecifier,
+    /// An opening parenthesis or a keyword opening a block, such as `CASE`
+    OpenParen,
+    /// A closing parenthesis or a keyword closing a block, such as `END`
+    CloseParen,
+    /// A keyword that doesn't affect the layout
+    Reserved,
+    /// A keyword starting a clause on a new line and indenting its arguments, such as `SELECT`
+    ReservedTopLevel,
+    /// A keyword starting a clause on a new line without indenting, such as `UNION`
+    ReservedTopLevelNoIndent,
+    /// A keyword starting a new line, such as `AND` or a `JOIN`
+    ReservedNewline,
+    /// A keyword followed by a new line, such as `DO UPDATE SET`
+    ReservedNewlineAfter,
+    /// A keyword opening a procedural block, such as `BEGIN`, `LOOP` or the `THEN` of an `IF`
+    BlockStart,
+    /// A keyword separating the parts of a procedural block, such as `EXCEPTION` or `ELSE`
+    BlockMiddle,
+    /// A keyword closing a procedural block, such as `END IF`
+    BlockEnd,
+}
+
+impl From<tokenizer::TokenKind> for TokenKind {
+    fn from(kind: tokenizer::TokenKind) -> Self {
+        match kind {
+            tokenizer::TokenKind::TypeSpecifier => TokenKind::TypeSpecifier,
+            tokenizer::TokenKind::Whitespace => TokenKind::Whitespace,
+            tokenizer::TokenKind::String => TokenKind::String,
+            tokenizer::TokenKind::Reserved => TokenKind::Reserved,
+            tokenizer::TokenKind::ReservedTopLevel => TokenKind::ReservedTopLevel,
+            tokenizer::TokenKind::ReservedTopLevelNoIndent => TokenKind::ReservedTopLevelNoIndent,
+            tokenizer::TokenKind::ReservedNewline => TokenKind::ReservedNewline,
+            tokenizer::TokenKind::ReservedNewlineAfter => TokenKind::ReservedNewlineAfter,
+            tokenizer::TokenKind::Operator => TokenKind::Operator,
+            tokenizer::TokenKind::OpenParen => TokenKind::OpenParen,
+            tokenizer::TokenKind::CloseParen => TokenKind::CloseParen,
+            tokenizer::TokenKind::LineComment => TokenKind::LineComment,
+            tokenizer::TokenKind::BlockComment => TokenKind::BlockComment,
+            tokenizer::TokenKind::Number => TokenKind::Number,
+            tokenizer::TokenKind::Placeholder => TokenKind::Placeholder,
+            tokenizer::TokenKind::Word => TokenKind::Word,
+            // Joins are always resolved to one of the reserved kinds by the tokenizer
+            tokenizer::TokenKind::Join => TokenKind::ReservedNewline,
+            tokenizer::TokenKind::BlockStart => TokenKind::BlockStart,
+            tokenizer::TokenKind::BlockMiddle => TokenKind::BlockMiddle,
+            tokenizer::TokenKind::BlockEnd => TokenKind::BlockEnd,
+            tokenizer::TokenKind::ReservedStatement => TokenKind::ReservedNewline,
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub(crate) struct SpanInfo {
     pub full_span: usize,
@@ -1655,188 +2073,2154 @@ mod tests {
     }
 
     #[test]
-    fn it_recognizes_question_numbered_placeholders() {
-        let input = "SELECT ?1, ?25, ?;";
-        let options = FormatOptions::default();
+    fn it_formats_tsql_batches() {
+        let input = indoc!(
+            "
+            declare @t table (id int, name nvarchar(50));
+            begin tran;
+            begin try
+            insert into #temp (id) output inserted.id into @t (id) select top (10) id from ##global with (nolock);
+            if @i > 0 begin print 'a'; end else begin print 'b'; end
+            while @i < 10 begin set @i = @i + 1; end
+            commit tran;
+            end try
+            begin catch rollback transaction; throw; end catch
+            GO"
+        );
+        let options = FormatOptions {
+            dialect: Dialect::SQLServer,
+            uppercase: Some(true),
+            ..Default::default()
+        };
         let expected = indoc!(
             "
-            SELECT
-              ?1,
-              ?25,
-              ?;"
+            DECLARE @t TABLE (id int, name nvarchar(50));
+            BEGIN TRAN;
+            BEGIN TRY
+              INSERT INTO
+                #temp (id)
+              OUTPUT
+                inserted.id INTO @t (id)
+              SELECT TOP (10)
+                id
+              FROM
+                ##global WITH (NOLOCK);
+              IF @i > 0
+              BEGIN
+                PRINT 'a';
+              END
+              ELSE
+              BEGIN
+                PRINT 'b';
+              END
+              WHILE @i < 10
+              BEGIN
+                SET
+                  @i = @i + 1;
+              END
+              COMMIT TRAN;
+            END TRY
+            BEGIN CATCH
+              ROLLBACK TRANSACTION;
+              THROW;
+            END CATCH
+            GO"
         );
 
         assert_eq!(format(input, &QueryParams::None, &options), expected);
+        assert!(try_format(input, &QueryParams::None, &options).is_ok());
     }
 
     #[test]
-    fn it_recognizes_question_numbered_placeholders_with_param_values() {
-        let input = "SELECT ?1, ?2, ?0;";
-        let params = vec![
-            "first".to_string(),
-            "second".to_string(),
-            "third".to_string(),
-        ];
-        let options = FormatOptions::default();
+    fn it_formats_tsql_merge_and_top() {
+        let input = "MERGE target AS t USING source AS s ON t.id = s.id WHEN MATCHED THEN UPDATE SET t.a = s.a WHEN NOT MATCHED BY TARGET THEN INSERT (id, a) VALUES (s.id, s.a) WHEN NOT MATCHED BY SOURCE THEN DELETE; SELECT TOP 5 PERCENT * FROM t; EXEC p @x OUTPUT;";
+        let options = FormatOptions {
+            dialect: Dialect::SQLServer,
+            ..Default::default()
+        };
         let expected = indoc!(
             "
-            SELECT
-              second,
-              third,
-              first;"
-        );
-
-        assert_eq!(
-            format(input, &QueryParams::Indexed(params), &options),
-            expected
+            MERGE
+              target AS t
+            USING
+              source AS s ON t.id = s.id
+            WHEN MATCHED THEN
+            UPDATE
+            SET
+              t.a = s.a
+            WHEN NOT MATCHED BY TARGET THEN
+            INSERT
+              (id, a)
+            VALUES
+              (s.id, s.a)
+            WHEN NOT MATCHED BY SOURCE THEN
+            DELETE;
+            SELECT TOP 5 PERCENT
+              *
+            FROM
+              t;
+            EXEC p @x OUTPUT;"
         );
 
-        format("?62666666121266666612", &QueryParams::None, &options);
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
     }
 
     #[test]
-    fn it_recognizes_question_indexed_placeholders_with_param_values() {
-        let input = "SELECT ?, ?, ?;";
-        let params = vec![
-            "first".to_string(),
-            "second".to_string(),
-            "third".to_string(),
-        ];
-        let options = FormatOptions::default();
-        let expected = indoc!(
+    fn it_formats_tsql_syntax_as_before_in_the_generic_dialect() {
+        let input = indoc!(
             "
-            SELECT
-              first,
-              second,
-              third;"
-        );
-
-        assert_eq!(
-            format(input, &QueryParams::Indexed(params), &options),
-            expected
+            MERGE t USING s ON t.id = s.id WHEN MATCHED THEN UPDATE SET a = 1 WHEN NOT MATCHED THEN INSERT (id) VALUES (s.id);
+            SELECT TOP (10) a FROM t WITH (NOLOCK);
+            DELETE FROM t OUTPUT deleted.id INTO @ids;
+            BEGIN TRY SELECT 1; END TRY BEGIN CATCH THROW; END CATCH
+            BEGIN TRAN; IF @a > 0 PRINT 'a' ELSE PRINT 'b'; COMMIT TRAN;
+            SELECT a FROM #temp"
         );
-    }
-
-    #[test]
-    fn it_recognizes_dollar_sign_numbered_placeholders() {
-        let input = "SELECT $1, $2;";
         let options = FormatOptions::default();
         let expected = indoc!(
             "
+            MERGE t USING s ON t.id = s.id
+            WHEN MATCHED THEN
+            UPDATE
+            SET
+              a = 1
+              WHEN NOT MATCHED THEN
+            INSERT
+              (id)
+            VALUES
+              (s.id);
             SELECT
-              $1,
-              $2;"
+              TOP (10) a
+            FROM
+              t
+            WITH
+            (NOLOCK);
+            DELETE FROM
+              t OUTPUT deleted.id INTO @ids;
+            BEGIN
+            TRY
+            SELECT
+              1;
+            END TRY
+            BEGIN
+            CATCH THROW;
+            END CATCH
+            BEGIN
+            TRAN;
+            IF @a > 0 PRINT 'a'
+            ELSE PRINT 'b';
+            COMMIT TRAN;
+            SELECT
+              a
+            FROM
+              #temp"
         );
 
         assert_eq!(format(input, &QueryParams::None, &options), expected);
     }
 
     #[test]
-    fn it_recognizes_dollar_sign_alphanumeric_placeholders() {
-        let input = "SELECT $hash, $foo, $bar;";
-        let options = FormatOptions::default();
+    fn it_starts_tsql_statements_without_semicolons() {
+        let input = indoc!(
+            "
+            SELECT a FROM t
+            IF @x = 1 BEGIN SET @i = @i + 1
+            PRINT @i END
+            ELSE IF @x = 2 PRINT 'two' ELSE RETURN
+            DECLARE @y INT = 1
+            WHILE @y < 3 BEGIN EXEC p @y IF @y = 2 BREAK END
+            DROP TABLE IF EXISTS #t"
+        );
+        let options = FormatOptions {
+            dialect: Dialect::SQLServer,
+            ..Default::default()
+        };
         let expected = indoc!(
             "
             SELECT
-              $hash,
-              $foo,
-              $bar;"
+              a
+            FROM
+              t
+            IF @x = 1
+            BEGIN
+              SET
+                @i = @i + 1
+              PRINT @i
+            END
+            ELSE IF @x = 2
+            PRINT 'two'
+            ELSE RETURN
+            DECLARE @y INT = 1
+            WHILE @y < 3
+            BEGIN
+              EXEC p @y
+              IF @y = 2
+              BREAK
+            END
+            DROP TABLE IF EXISTS
+              #t"
         );
 
         assert_eq!(format(input, &QueryParams::None, &options), expected);
     }
 
     #[test]
-    fn it_recognizes_dollar_sign_numbered_placeholders_with_param_values() {
-        let input = "SELECT $2, $3, $1, $named, $4, $alias;";
-        let params = vec![
-            "first".to_string(),
-            "second".to_string(),
-            "third".to_string(),
-            "4th".to_string(),
-        ];
-        let options = FormatOptions::default();
+    fn it_keeps_tsql_if_exists_guards_in_their_statement() {
+        let input = "CREATE EXTERNAL TABLE IF NOT EXISTS sales (id INT) WITH (LOCATION = '/sales/'); CREATE SCHEMA IF NOT EXISTS s; IF OBJECT_ID('t') IS NULL PRINT 'missing'";
+        let options = FormatOptions {
+            dialect: Dialect::SQLServer,
+            ..Default::default()
+        };
         let expected = indoc!(
             "
-            SELECT
-              second,
-              third,
-              first,
-              $named,
-              4th,
-              $alias;"
+            CREATE EXTERNAL TABLE IF NOT EXISTS sales (id INT) WITH (LOCATION = '/sales/');
+            CREATE SCHEMA IF NOT EXISTS s;
+            IF OBJECT_ID('t') IS NULL
+            PRINT 'missing'"
         );
 
-        assert_eq!(
-            format(input, &QueryParams::Indexed(params), &options),
-            expected
-        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
     }
 
     #[test]
-    fn it_recognizes_dollar_sign_alphanumeric_placeholders_with_param_values() {
-        let input = "SELECT $hash, $salt, $1, $2;";
-        let params = vec![
-            ("hash".to_string(), "hash value".to_string()),
-            ("salt".to_string(), "salt value".to_string()),
-            ("1".to_string(), "number 1".to_string()),
-            ("2".to_string(), "number 2".to_string()),
-        ];
-        let options = FormatOptions::default();
+    fn it_keeps_tsql_output_parameters_out_of_clauses() {
+        let input =
+            "CREATE PROCEDURE p @x INT OUTPUT AS UPDATE t SET a = 1 OUTPUT inserted.a WHERE b = @x";
+        let options = FormatOptions {
+            dialect: Dialect::SQLServer,
+            ..Default::default()
+        };
         let expected = indoc!(
             "
-            SELECT
-              hash value,
-              salt value,
-              number 1,
-              number 2;"
+            CREATE PROCEDURE p @x INT OUTPUT AS
+            UPDATE
+              t
+            SET
+              a = 1
+            OUTPUT
+              inserted.a
+            WHERE
+              b = @x"
         );
 
-        assert_eq!(
-            format(input, &QueryParams::Named(params), &options),
-            expected
-        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
     }
 
     #[test]
-    fn it_recognizes_braced_placeholders_with_param_values() {
-        let input = "SELECT {a}, {b}, {c};";
-        let params = vec![
-            ("a".to_string(), "first".to_string()),
-            ("b".to_string(), "second".to_string()),
-            ("c".to_string(), "third".to_string()),
-        ];
-        let options = FormatOptions::default();
+    fn it_recognizes_mysql_comments_and_strings() {
+        let input = indoc!(
+            r#"
+            SELECT 'it''s \\', "a \" b", 1--1 # comment
+            FROM t -- comment
+            WHERE a = '\''"#
+        );
+        let options = FormatOptions {
+            dialect: Dialect::MySql,
+            ..Default::default()
+        };
         let expected = indoc!(
-            "
+            r#"
             SELECT
-              first,
-              second,
-              third;"
+              'it''s \\',
+              "a \" b",
+              1 - -1 # comment
+            FROM
+              t -- comment
+            WHERE
+              a = '\''"#
         );
 
-        assert_eq!(
-            format(input, &QueryParams::Named(params), &options),
-            expected
-        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
     }
+
     #[test]
-    fn it_formats_raw_sql_and_conditional_queries() {
-        let input = "SELECT {foo: &[T]}, {b.c()}, {d.e};";
-        let options = FormatOptions::default();
+    fn it_formats_mysql_upserts_joins_and_index_hints() {
+        let input = "INSERT INTO t (a, b) VALUES (1, 2) ON DUPLICATE KEY UPDATE a = VALUES(a), b = @b := 2; SELECT STRAIGHT_JOIN a FROM t1 FORCE INDEX (idx_a) STRAIGHT_JOIN t2 IGNORE INDEX FOR JOIN (idx_b) ON t1.id = t2.id";
+        let options = FormatOptions {
+            dialect: Dialect::MySql,
+            ..Default::default()
+        };
         let expected = indoc!(
             "
+            INSERT INTO
+              t (a, b)
+            VALUES
+              (1, 2)
+            ON DUPLICATE KEY UPDATE
+              a = VALUES (a),
+              b = @b := 2;
             SELECT
-              {foo: &[T]},
-              {b.c()},
-              {d.e};"
+              STRAIGHT_JOIN a
+            FROM
+              t1 FORCE INDEX (idx_a)
+              STRAIGHT_JOIN t2 IGNORE INDEX FOR JOIN (idx_b) ON t1.id = t2.id"
         );
 
         assert_eq!(format(input, &QueryParams::None, &options), expected);
     }
 
     #[test]
-    fn it_formats_query_with_go_batch_separator() {
+    fn it_formats_mysql_delimiter_blocks() {
+        let input = indoc!(
+            "
+            DELIMITER //
+            CREATE PROCEDURE p() BEGIN SELECT a FROM t; END //
+            delimiter ;
+            SELECT 1;"
+        );
+        let options = FormatOptions {
+            dialect: Dialect::MySql,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            DELIMITER //
+            CREATE PROCEDURE p()
+            BEGIN
+              SELECT
+                a
+              FROM
+                t;
+            END //
+            delimiter ;
+            SELECT
+              1;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_indents_mysql_procedure_bodies() {
+        let input = indoc!(
+            "
+            BEGIN; UPDATE t SET a = 1; COMMIT;
+            DELIMITER $$
+            CREATE PROCEDURE fill(IN n INT) BEGIN DECLARE i INT DEFAULT 0; WHILE i < n DO INSERT INTO t VALUES (i); SET i = i + 1; END WHILE; IF n > 10 THEN SELECT 'many'; ELSE SELECT 'few'; END IF; END$$
+            DELIMITER ;"
+        );
+        let options = FormatOptions {
+            dialect: Dialect::MySql,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            BEGIN;
+            UPDATE
+              t
+            SET
+              a = 1;
+            COMMIT;
+            DELIMITER $$
+            CREATE PROCEDURE fill(IN n INT)
+            BEGIN
+              DECLARE i INT DEFAULT 0;
+              WHILE i < n DO
+                INSERT INTO
+                  t
+                VALUES
+                  (i);
+                SET
+                  i = i + 1;
+              END WHILE;
+              IF n > 10 THEN
+                SELECT
+                  'many';
+              ELSE
+                SELECT
+                  'few';
+              END IF;
+            END $$
+            DELIMITER ;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_sqlite_conflict_clauses() {
+        let input = "INSERT OR REPLACE INTO t (key, replace) VALUES (1, 2); INSERT INTO t (id, n) VALUES (1, 2) ON CONFLICT (id) DO UPDATE SET n = excluded.n; UPDATE OR IGNORE t SET key = 1";
+        let options = FormatOptions {
+            dialect: Dialect::Sqlite,
+            uppercase: Some(true),
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            INSERT OR REPLACE INTO
+              t (key, replace)
+            VALUES
+              (1, 2);
+            INSERT INTO
+              t (id, n)
+            VALUES
+              (1, 2)
+            ON CONFLICT
+              (id)
+            DO UPDATE SET
+              n = excluded.n;
+            UPDATE OR IGNORE
+              t
+            SET
+              key = 1"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_sqlite_tables_and_commands() {
+        let input = indoc!(
+            "
+            pragma foreign_keys = on;
+            attach database 'other.db' as other;
+            CREATE TEMP TABLE t (key text primary key, b text unique on conflict replace, strict int) without rowid, strict;
+            select [key], replace(b, 'x', 'y') from t where b glob 'x*' and key notnull;"
+        );
+        let options = FormatOptions {
+            dialect: Dialect::Sqlite,
+            uppercase: Some(true),
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            PRAGMA foreign_keys = ON;
+            ATTACH DATABASE 'other.db' AS other;
+            CREATE TEMP TABLE t (
+              key text PRIMARY KEY,
+              b text UNIQUE ON CONFLICT REPLACE,
+              strict int
+            ) WITHOUT ROWID, STRICT;
+            SELECT
+              [key],
+              replace(b, 'x', 'y')
+            FROM
+              t
+            WHERE
+              b GLOB 'x*'
+              AND key NOTNULL;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_recognizes_sqlite_placeholders() {
+        let input = "SELECT ?2, ?1, :a, @b, $c, $d::e(f);";
+        let options = FormatOptions {
+            dialect: Dialect::Sqlite,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              second,
+              first,
+              :a,
+              @b,
+              $c,
+              $d::e(f);"
+        );
+        let params = vec!["first".to_string(), "second".to_string()];
+
+        assert_eq!(
+            format(input, &QueryParams::Indexed(params), &options),
+            expected
+        );
+
+        let params = QueryParams::Named(vec![
+            ("a".to_string(), "1".to_string()),
+            ("d::e(f)".to_string(), "2".to_string()),
+        ]);
+        assert_eq!(
+            format("SELECT :a, $d::e(f)", &params, &options),
+            "SELECT\n  1,\n  2"
+        );
+    }
+
+    #[test]
+    fn it_formats_sqlite_syntax_as_before_in_the_generic_dialect() {
+        let input = indoc!(
+            "
+            INSERT OR REPLACE INTO t (a) VALUES (1);
+            REPLACE INTO t (a) VALUES (2);
+            CREATE TEMP TABLE t (a INT, strict INT) WITHOUT ROWID, STRICT;
+            UPDATE OR IGNORE t SET a = ?1 WHERE b = :b;"
+        );
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            INSERT
+              OR REPLACE INTO t (a)
+            VALUES
+              (1);
+            REPLACE INTO t (a)
+            VALUES
+              (2);
+            CREATE TEMP TABLE t (a INT, strict INT) WITHOUT ROWID,
+            STRICT;
+            UPDATE
+              OR IGNORE t
+            SET
+              a = ?1
+            WHERE
+              b = :b;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_oracle_blocks() {
+        let input = indoc!(
+            r#"
+            CREATE OR REPLACE PROCEDURE p (a in number) is v number := 0; begin
+            if a > 1 then v := 1; elsif a > 0 then v := 2; else null; end if;
+            for i in 1..10 loop EXIT when i > a; end loop;
+            exception when no_data_found then null; when others then raise; end p;
+            /
+            declare v number; begin v := case when 1 = 1 then 1 else 2 end; end;
+            /"#
+        );
+        let options = FormatOptions {
+            dialect: Dialect::Oracle,
+            uppercase: Some(true),
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            CREATE OR REPLACE PROCEDURE p (a IN number) IS
+              v number := 0;
+            BEGIN
+              IF a > 1 THEN
+                v := 1;
+              ELSIF a > 0 THEN
+                v := 2;
+              ELSE
+                NULL;
+              END IF;
+              FOR i IN 1..10 LOOP
+                EXIT WHEN i > a;
+              END LOOP;
+            EXCEPTION
+              WHEN no_data_found THEN
+                NULL;
+              WHEN others THEN
+                raise;
+            END p;
+            /
+            DECLARE
+              v number;
+            BEGIN
+              v := CASE
+                WHEN 1 = 1 THEN 1
+                ELSE 2
+              END;
+            END;
+            /"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+        assert!(try_format(input, &QueryParams::None, &options).is_ok());
+    }
+
+    #[test]
+    fn it_formats_oracle_packages() {
+        let input = "CREATE PACKAGE BODY pkg AS PROCEDURE p(x NUMBER); FUNCTION f RETURN NUMBER IS BEGIN SELECT a INTO x FROM t; RETURN 1; END f; END pkg;";
+        let options = FormatOptions {
+            dialect: Dialect::Oracle,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            CREATE PACKAGE BODY pkg AS
+              PROCEDURE p(x NUMBER);
+              FUNCTION f RETURN NUMBER IS
+              BEGIN
+                SELECT
+                  a INTO x
+                FROM
+                  t;
+                RETURN 1;
+              END f;
+            END pkg;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_oracle_syntax_as_before_in_the_generic_dialect() {
+        let input = indoc!(
+            "
+            CREATE OR REPLACE PACKAGE BODY pkg AS PROCEDURE p(x NUMBER); END pkg;
+            SELECT q'[it's]', a FROM t START WITH a = 1 CONNECT BY PRIOR a = b;
+            DECLARE v NUMBER; BEGIN v := 1; END;
+            /"
+        );
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            CREATE
+            OR REPLACE PACKAGE BODY pkg AS PROCEDURE p(x NUMBER);
+            END pkg;
+            SELECT
+              q '[it' s ] ',
+              a
+            FROM
+              t START
+            WITH
+            a = 1 CONNECT BY PRIOR a = b;
+            DECLARE
+            v NUMBER;
+            BEGIN
+            v := 1;
+            END;
+            /"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_keeps_slashes_that_do_not_run_oracle_blocks() {
+        let input = indoc!(
+            "
+            /* comment */
+            SELECT a
+            / 2 FROM t
+            /"
+        );
+        let options = FormatOptions {
+            dialect: Dialect::Oracle,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            /* comment */
+            SELECT
+              a / 2
+            FROM
+              t
+            /"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_oracle_hierarchical_queries_and_strings() {
+        let input = r"SELECT q'[it's]', q'{a ' b}', 'C:\temp\' FROM emp START WITH mgr IS NULL CONNECT BY PRIOR id = mgr";
+        let options = FormatOptions {
+            dialect: Dialect::Oracle,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            r"
+            SELECT
+              q'[it's]',
+              q'{a ' b}',
+              'C:\temp\'
+            FROM
+              emp
+            START WITH
+              mgr IS NULL
+            CONNECT BY
+              PRIOR id = mgr"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_bigquery_scripts() {
+        let input = indoc!(
+            "
+            declare x int64 default 0;
+            if x > 0 then select 1; elseif x < 0 then select if(x < -1, 2, 3); else
+            while x < 3 do set x = x + 1; end while; end if;
+            begin select 1 / x; exception when error then select @@error.message; end;"
+        );
+        let options = FormatOptions {
+            dialect: Dialect::BigQuery,
+            uppercase: Some(true),
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            DECLARE x int64 DEFAULT 0;
+            IF x > 0 THEN
+              SELECT
+                1;
+            ELSEIF x < 0 THEN
+              SELECT
+                IF (x < -1, 2, 3);
+            ELSE
+              WHILE x < 3 DO
+                SET
+                  x = x + 1;
+              END WHILE;
+            END IF;
+            BEGIN
+              SELECT
+                1 / x;
+            EXCEPTION
+              WHEN ERROR THEN
+                SELECT
+                  @@error.message;
+            END;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+        assert!(try_format(input, &QueryParams::None, &options).is_ok());
+    }
+
+    #[test]
+    fn it_recognizes_bigquery_types_and_strings() {
+        let input = r#"SELECT * EXCEPT (a) REPLACE (b + 1 AS b), ARRAY<STRUCT<x INT64, y STRING>>[], r'\d+', b"\x00", """a 'b' c""" FROM `my-project.dataset.table` WHERE c[OFFSET(0)] = 1 QUALIFY ROW_NUMBER() OVER w = 1"#;
+        let options = FormatOptions {
+            dialect: Dialect::BigQuery,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            r#"
+            SELECT
+              * EXCEPT (a) REPLACE (b + 1 AS b),
+              ARRAY<STRUCT<x INT64, y STRING>>[],
+              r'\d+',
+              b"\x00",
+              """a 'b' c"""
+            FROM
+              `my-project.dataset.table`
+            WHERE
+              c[OFFSET (0)] = 1
+            QUALIFY
+              ROW_NUMBER() OVER w = 1"#
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_bigquery_syntax_as_before_in_the_generic_dialect() {
+        let input = indoc!(
+            "
+            SELECT a, ROW_NUMBER() OVER (PARTITION BY b) AS rn FROM t QUALIFY rn = 1 UNION DISTINCT SELECT * EXCEPT (c) FROM u;
+            BEGIN TRANSACTION; SELECT 1 INTERSECT DISTINCT SELECT 2 EXCEPT DISTINCT SELECT 3; COMMIT TRANSACTION;
+            DECLARE x INT64 DEFAULT 0"
+        );
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              a,
+              ROW_NUMBER() OVER (
+                PARTITION BY
+                  b) AS rn
+            FROM
+              t QUALIFY rn = 1
+            UNION
+            DISTINCT
+            SELECT
+              * EXCEPT (c)
+            FROM
+              u;
+            BEGIN
+            TRANSACTION;
+            SELECT
+              1
+            INTERSECT
+            DISTINCT
+            SELECT
+              2 EXCEPT DISTINCT
+            SELECT
+              3;
+            COMMIT TRANSACTION;
+            DECLARE
+            x INT64 DEFAULT 0"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_snowflake_semi_structured_data() {
+        let input = indoc!(
+            r#"
+            CREATE PROCEDURE p() RETURNS STRING LANGUAGE JAVASCRIPT AS $$
+              return 'a;b';
+            $$;
+            SELECT f.value:name::string, src:a.b[0]."C"::int FROM t, LATERAL FLATTEN(input => t.items) f QUALIFY ROW_NUMBER() OVER (PARTITION BY t.id ORDER BY f.index) = 1"#
+        );
+        let options = FormatOptions {
+            dialect: Dialect::Snowflake,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            r#"
+            CREATE PROCEDURE p() RETURNS STRING LANGUAGE JAVASCRIPT AS $$
+              return 'a;b';
+            $$;
+            SELECT
+              f.value:name::string,
+              src:a.b[0]."C"::int
+            FROM
+              t,
+              LATERAL FLATTEN(input => t.items) f
+            QUALIFY
+              ROW_NUMBER() OVER (
+                PARTITION BY
+                  t.id
+                ORDER BY
+                  f.index
+              ) = 1"#
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_snowflake_copy_and_match_recognize() {
+        let input = "COPY INTO t FROM @stage/path/ FILE_FORMAT = (TYPE = 'CSV') PATTERN = '.*csv' ON_ERROR = 'CONTINUE'; CREATE STAGE s URL = 's3://b/' FILE_FORMAT = f; SELECT * FROM t MATCH_RECOGNIZE (ORDER BY d MEASURES FIRST(d) AS s ONE ROW PER MATCH PATTERN (a b+) DEFINE b AS p < LAG(p))";
+        let options = FormatOptions {
+            dialect: Dialect::Snowflake,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            COPY INTO
+              t
+            FROM
+              @stage/path/
+              FILE_FORMAT = (TYPE = 'CSV')
+              PATTERN = '.*csv'
+              ON_ERROR = 'CONTINUE';
+            CREATE STAGE s
+            URL = 's3://b/'
+            FILE_FORMAT = f;
+            SELECT
+              *
+            FROM
+              t MATCH_RECOGNIZE (
+                ORDER BY
+                  d
+                MEASURES
+                  FIRST(d) AS s
+                ONE ROW PER MATCH
+                PATTERN
+                  (a b+)
+                DEFINE
+                  b AS p < LAG(p)
+              )"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_snowflake_first_like_a_function() {
+        let input = "SELECT FIRST(a) AS f FROM t ORDER BY a NULLS FIRST";
+        let options = FormatOptions {
+            dialect: Dialect::Snowflake,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              FIRST(a) AS f
+            FROM
+              t
+            ORDER BY
+              a NULLS FIRST"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_snowflake_syntax_as_before_in_the_generic_dialect() {
+        let input = indoc!(
+            "
+            CREATE STAGE my_stage URL = 's3://b/';
+            COPY INTO t FROM @stage FILE_FORMAT = (TYPE = 'CSV') ON_ERROR = 'CONTINUE';
+            SELECT v:name::string, x::int FROM t QUALIFY ROW_NUMBER() OVER (ORDER BY x) = 1;
+            SELECT * FROM t MATCH_RECOGNIZE (MEASURES FIRST(d) AS s PATTERN (a b+) DEFINE b AS p < 1)"
+        );
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            CREATE STAGE my_stage URL = 's3://b/';
+            COPY INTO t
+            FROM
+              @stage FILE_FORMAT = (TYPE = 'CSV') ON_ERROR = 'CONTINUE';
+            SELECT
+              v :name:: string,
+              x::int
+            FROM
+              t QUALIFY ROW_NUMBER() OVER (
+                ORDER BY
+                  x) = 1;
+            SELECT
+              *
+            FROM
+              t MATCH_RECOGNIZE (
+                MEASURES FIRST (d) AS s PATTERN (a b +) DEFINE b AS p < 1
+              )"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_clickhouse_tables() {
+        let input = "CREATE TABLE t (id UInt64, d Date, PRIMARY KEY id) ENGINE = MergeTree() PARTITION BY toYYYYMM(d) ORDER BY (id, d) SAMPLE BY id TTL d + INTERVAL 1 MONTH SETTINGS index_granularity = 8192;";
+        let options = FormatOptions {
+            dialect: Dialect::ClickHouse,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            CREATE TABLE t (id UInt64, d Date, PRIMARY KEY id)
+            ENGINE = MergeTree()
+            PARTITION BY
+              toYYYYMM(d)
+            ORDER BY
+              (id, d)
+            SAMPLE BY
+              id
+            TTL
+              d + INTERVAL 1 MONTH
+            SETTINGS
+              index_granularity = 8192;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_clickhouse_queries() {
+        let input = "SELECT arrayMap(x -> x + 1, nums) AS n, tag FROM t FINAL SAMPLE 0.1 ARRAY JOIN tags AS tag PREWHERE d > {start:Date} WHERE id = {id: UInt64} LIMIT 2 BY id SETTINGS max_threads = 4 FORMAT JSONEachRow";
+        let params = vec![
+            ("start".to_string(), "'2024-01-01'".to_string()),
+            ("id".to_string(), "1".to_string()),
+        ];
+        let options = FormatOptions {
+            dialect: Dialect::ClickHouse,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              arrayMap(x -> x + 1, nums) AS n,
+              tag
+            FROM
+              t FINAL SAMPLE 0.1
+              ARRAY JOIN tags AS tag
+            PREWHERE
+              d > '2024-01-01'
+            WHERE
+              id = 1
+            LIMIT
+              2 BY id
+            SETTINGS
+              max_threads = 4
+            FORMAT
+              JSONEachRow"
+        );
+
+        assert_eq!(
+            format(input, &QueryParams::Named(params), &options),
+            expected
+        );
+    }
+
+    #[test]
+    fn it_formats_clickhouse_syntax_as_before_in_the_generic_dialect() {
+        let input = indoc!(
+            "
+            CREATE TABLE t (id UInt64) ENGINE = MergeTree() ORDER BY id SETTINGS index_granularity = 8192;
+            SELECT tag FROM t FINAL SAMPLE 0.1 ARRAY JOIN tags AS tag PREWHERE d > 1 LIMIT 2 BY id FORMAT JSONEachRow"
+        );
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            CREATE TABLE t (id UInt64) ENGINE = MergeTree()
+            ORDER BY
+              id SETTINGS index_granularity = 8192;
+            SELECT
+              tag
+            FROM
+              t FINAL SAMPLE 0.1 ARRAY
+              JOIN tags AS tag PREWHERE d > 1
+            LIMIT
+              2 BY id FORMAT JSONEachRow"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_duckdb_queries() {
+        let input = "FROM t SELECT * EXCLUDE (a) REPLACE (b + 1 AS b), COLUMNS('x_.*') ASOF JOIN u ON t.ts >= u.ts POSITIONAL JOIN v WHERE id = $id QUALIFY row_number() OVER w = 1; INSTALL httpfs; COPY t TO 'out.parquet' (FORMAT parquet)";
+        let params = vec![("id".to_string(), "1".to_string())];
+        let options = FormatOptions {
+            dialect: Dialect::DuckDb,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            FROM
+              t
+            SELECT
+              * EXCLUDE (a) REPLACE (b + 1 AS b),
+              COLUMNS('x_.*')
+              ASOF JOIN u ON t.ts >= u.ts
+              POSITIONAL JOIN v
+            WHERE
+              id = 1
+            QUALIFY
+              row_number() OVER w = 1;
+            INSTALL httpfs;
+            COPY t TO 'out.parquet' (FORMAT parquet)"
+        );
+
+        assert_eq!(
+            format(input, &QueryParams::Named(params), &options),
+            expected
+        );
+    }
+
+    #[test]
+    fn it_formats_duckdb_pivots_and_literals() {
+        let input = "PIVOT t ON year USING sum(amount) GROUP BY region; UNPIVOT t ON jan, feb INTO NAME month VALUE amount; SELECT [1, 2][1:2] AS l, {'a': 1, 'b': [x -> x * 2]} AS s FROM t";
+        let options = FormatOptions {
+            dialect: Dialect::DuckDb,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            PIVOT
+              t
+            ON
+              year
+            USING
+              sum(amount)
+            GROUP BY
+              region;
+            UNPIVOT
+              t
+            ON
+              jan,
+              feb
+            INTO
+              NAME month VALUE amount;
+            SELECT
+              [1, 2][1:2] AS l,
+              {'a': 1, 'b': [x -> x * 2]} AS s
+            FROM
+              t"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_duckdb_syntax_as_before_in_the_generic_dialect() {
+        let input = indoc!(
+            "
+            FROM t SELECT * EXCLUDE (a) ASOF JOIN u ON t.ts >= u.ts POSITIONAL JOIN v ANTI JOIN w USING (id) QUALIFY row_number() OVER w = 1;
+            PIVOT t ON year USING sum(amount) GROUP BY region;
+            SELECT [1, 2][1:2] AS l, {'a': 1} AS s FROM t"
+        );
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            FROM
+              t
+            SELECT
+              * EXCLUDE (a)
+              ASOF JOIN u ON t.ts >= u.ts POSITIONAL
+              JOIN v ANTI
+              JOIN w USING (id) QUALIFY row_number() OVER w = 1;
+            PIVOT t ON year USING sum(amount)
+            GROUP BY
+              region;
+            SELECT
+              [ 1,
+              2 ] [ 1 :2 ] AS l,
+              {'a': 1} AS s
+            FROM
+              t"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_spark_queries() {
+        let input = "INSERT OVERWRITE TABLE db.t PARTITION (dt = '${dt}') SELECT `my col`, t.item FROM s LATERAL VIEW OUTER explode(s.items) t AS item WHERE x = ${limit} DISTRIBUTE BY `my col` SORT BY t.item";
+        let params = vec![
+            ("dt".to_string(), "2024-01-01".to_string()),
+            ("limit".to_string(), "10".to_string()),
+        ];
+        let options = FormatOptions {
+            dialect: Dialect::Spark,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            INSERT OVERWRITE TABLE
+              db.t PARTITION (dt = '${dt}')
+            SELECT
+              `my col`,
+              t.item
+            FROM
+              s
+              LATERAL VIEW OUTER explode(s.items) t AS item
+            WHERE
+              x = 10
+            DISTRIBUTE BY
+              `my col`
+            SORT BY
+              t.item"
+        );
+
+        assert_eq!(
+            format(input, &QueryParams::Named(params), &options),
+            expected
+        );
+    }
+
+    #[test]
+    fn it_formats_spark_tables() {
+        let input = "CREATE OR REPLACE TABLE db.t (id BIGINT, `name` STRING) USING delta PARTITIONED BY (dt) LOCATION 's3://bucket/path' TBLPROPERTIES ('delta.appendOnly' = 'true'); CREATE EXTERNAL TABLE t (a INT) STORED AS PARQUET LOCATION '/x'";
+        let options = FormatOptions {
+            dialect: Dialect::Spark,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            CREATE OR REPLACE TABLE db.t (id BIGINT, `name` STRING)
+            USING delta
+            PARTITIONED BY (dt)
+            LOCATION 's3://bucket/path'
+            TBLPROPERTIES ('delta.appendOnly' = 'true');
+            CREATE EXTERNAL TABLE t (a INT)
+            STORED AS PARQUET
+            LOCATION '/x'"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_spark_syntax_as_before_in_the_generic_dialect() {
+        let input = indoc!(
+            "
+            INSERT OVERWRITE TABLE db.t PARTITION (dt = 1) SELECT a FROM s LATERAL VIEW OUTER explode(s.items) t AS item WHERE x = ${limit} DISTRIBUTE BY a SORT BY a;
+            INSERT INTO TABLE t SELECT 1;
+            CREATE OR REPLACE TABLE db.t (id BIGINT) USING delta PARTITIONED BY (dt) LOCATION 's3://b';
+            CREATE EXTERNAL TABLE t (a INT) STORED AS PARQUET"
+        );
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            INSERT
+              OVERWRITE TABLE db.t PARTITION (dt = 1)
+            SELECT
+              a
+            FROM
+              s LATERAL VIEW OUTER explode(s.items) t AS item
+            WHERE
+              x = $ {limit} DISTRIBUTE BY a SORT BY a;
+            INSERT INTO
+              TABLE t
+            SELECT
+              1;
+            CREATE
+            OR REPLACE TABLE db.t (id BIGINT) USING delta PARTITIONED BY (dt) LOCATION 's3://b';
+            CREATE EXTERNAL TABLE t (a INT) STORED AS PARQUET"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_trino_queries() {
+        let input = "SELECT ROW(1, 'a') AS r, MAP(ARRAY['a'], ARRAY[1]) AS m, transform(e.x, v -> v + 1) FROM orders FOR SYSTEM_TIME AS OF TIMESTAMP '2024-01-01' AS o CROSS JOIN UNNEST(o.items) WITH ORDINALITY AS e (x, n) WHERE o.n > 1; SELECT * FROM t TABLESAMPLE BERNOULLI (10)";
+        let options = FormatOptions {
+            dialect: Dialect::Trino,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              ROW(1, 'a') AS r,
+              MAP(ARRAY['a'], ARRAY[1]) AS m,
+              transform(e.x, v -> v + 1)
+            FROM
+              orders
+            FOR SYSTEM_TIME AS OF
+              TIMESTAMP '2024-01-01' AS o
+              CROSS JOIN UNNEST(o.items) WITH ORDINALITY AS e (x, n)
+            WHERE
+              o.n > 1;
+            SELECT
+              *
+            FROM
+              t
+            TABLESAMPLE
+              BERNOULLI (10)"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_breaks_trino_sampling_clauses_after_unnest_with_ordinality() {
+        let input = "SELECT e.x, e.n FROM orders AS o CROSS JOIN UNNEST(o.items) WITH ORDINALITY AS e (x, n) TABLESAMPLE SYSTEM (50) WHERE e.n > 1";
+        let options = FormatOptions {
+            dialect: Dialect::Trino,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              e.x,
+              e.n
+            FROM
+              orders AS o
+              CROSS JOIN UNNEST(o.items) WITH ORDINALITY AS e (x, n)
+            TABLESAMPLE
+              SYSTEM (50)
+            WHERE
+              e.n > 1"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_redshift_tables_copies_and_unloads() {
+        let input = "CREATE TABLE t (id INT ENCODE az64 DISTKEY, d DATE) DISTSTYLE KEY COMPOUND SORTKEY (d, id); COPY t FROM 's3://bucket/data/' IAM_ROLE 'arn:aws:iam::1:role/r' DELIMITER '|' IGNOREHEADER 1; UNLOAD ('SELECT * FROM t') TO 's3://bucket/prefix/' CREDENTIALS 'aws_iam_role=r' FORMAT AS PARQUET PARTITION BY (d)";
+        let options = FormatOptions {
+            dialect: Dialect::Redshift,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            CREATE TABLE t (id INT ENCODE az64 DISTKEY, d DATE)
+            DISTSTYLE KEY
+            COMPOUND SORTKEY (d, id);
+            COPY
+              t
+            FROM
+              's3://bucket/data/'
+              IAM_ROLE 'arn:aws:iam::1:role/r'
+              DELIMITER '|'
+              IGNOREHEADER 1;
+            UNLOAD
+              ('SELECT * FROM t')
+            TO
+              's3://bucket/prefix/'
+              CREDENTIALS 'aws_iam_role=r'
+              FORMAT AS PARQUET
+              PARTITION BY (d)"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_trino_and_redshift_syntax_as_before_in_the_generic_dialect() {
+        let input = indoc!(
+            "
+            SELECT e.x FROM orders AS o CROSS JOIN UNNEST(o.items) WITH ORDINALITY AS e (x, n);
+            CREATE TABLE t (id INT ENCODE az64 DISTKEY) DISTSTYLE KEY COMPOUND SORTKEY (id);
+            UNLOAD ('SELECT * FROM t') TO 's3://b/' FORMAT AS PARQUET PARTITION BY (d)"
+        );
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              e.x
+            FROM
+              orders AS o
+              CROSS JOIN UNNEST(o.items)
+            WITH
+            ORDINALITY AS e (x, n);
+            CREATE TABLE t (id INT ENCODE az64 DISTKEY) DISTSTYLE KEY COMPOUND SORTKEY (id);
+            UNLOAD ('SELECT * FROM t') TO 's3://b/' FORMAT AS PARQUET
+            PARTITION BY
+              (d)"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_postgresql_dollar_quoted_bodies() {
+        let input = "CREATE FUNCTION f(n int) RETURNS int AS $body$ DECLARE total int := 0; BEGIN IF n < 0 THEN RAISE EXCEPTION 'negative: %', n; ELSIF n = 0 THEN RETURN 0; END IF; FOREACH x IN ARRAY arr LOOP total := total + x; END LOOP; RETURN total; END; $body$ LANGUAGE plpgsql; CREATE FUNCTION g() RETURNS int AS $$ return 1 $$ LANGUAGE plpython3u;";
+        let mut options = FormatOptions {
+            dialect: Dialect::PostgreSql,
+            format_function_bodies: false,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            CREATE FUNCTION f(n int) RETURNS int AS $body$ DECLARE total int := 0; BEGIN IF n < 0 THEN RAISE EXCEPTION 'negative: %', n; ELSIF n = 0 THEN RETURN 0; END IF; FOREACH x IN ARRAY arr LOOP total := total + x; END LOOP; RETURN total; END; $body$ LANGUAGE plpgsql;
+            CREATE FUNCTION g() RETURNS int AS $$ return 1 $$ LANGUAGE plpython3u;"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+
+        options.format_function_bodies = true;
+        let expected = indoc!(
+            "
+            CREATE FUNCTION f(n int) RETURNS int AS $body$
+            DECLARE
+              total int := 0;
+            BEGIN
+              IF n < 0 THEN
+                RAISE EXCEPTION 'negative: %', n;
+              ELSIF n = 0 THEN
+                RETURN 0;
+              END IF;
+              FOREACH x IN ARRAY arr LOOP
+                total := total + x;
+              END LOOP;
+              RETURN total;
+            END;
+            $body$ LANGUAGE plpgsql;
+            CREATE FUNCTION g() RETURNS int AS $$ return 1 $$ LANGUAGE plpython3u;"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+
+        let input = "DO $$ BEGIN PERFORM pg_sleep(1); END $$; SELECT $1";
+        let expected = indoc!(
+            "
+            DO $$
+            BEGIN
+              PERFORM pg_sleep(1);
+            END
+            $$;
+            SELECT
+              $1"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_postgresql_function_bodies_by_default() {
+        let input = "CREATE FUNCTION abc() AS $$ SELECT * FROM table $$ LANGUAGE plpgsql;";
+        let options = FormatOptions {
+            dialect: Dialect::PostgreSql,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            CREATE FUNCTION abc() AS $$
+            SELECT
+              *
+            FROM
+              table
+            $$ LANGUAGE plpgsql;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_postgresql_ddl_clauses() {
+        let input = "CREATE POLICY p ON documents AS PERMISSIVE FOR SELECT TO app_user USING (owner = current_user) WITH CHECK (owner = current_user); CREATE TABLE booking (id bigint GENERATED ALWAYS AS IDENTITY (START WITH 10), during tstzrange, EXCLUDE USING gist (room WITH =, during WITH &&)); COPY booking (id, during) FROM STDIN WITH (FORMAT csv);\n1,a;  b\n2,  c\n\\.\nSELECT 1";
+        let options = FormatOptions {
+            dialect: Dialect::PostgreSql,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            CREATE POLICY p ON documents
+            AS PERMISSIVE
+            FOR SELECT
+            TO app_user
+            USING (owner = current_user)
+            WITH CHECK (owner = current_user);
+            CREATE TABLE booking (
+              id bigint GENERATED ALWAYS AS IDENTITY (START WITH 10),
+              during tstzrange,
+              EXCLUDE USING gist (room WITH =, during WITH &&)
+            );
+            COPY booking (id, during)
+            FROM
+              STDIN WITH (FORMAT csv);
+            1,a;  b
+            2,  c
+            \\.
+            SELECT
+              1"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_postgresql_syntax_as_before_in_the_generic_dialect() {
+        let input = indoc!(
+            "
+            CREATE TYPE mood AS ENUM ('sad', 'ok');
+            CREATE SEQUENCE s START WITH 1;
+            ALTER TABLE t ADD EXCLUDE USING gist (c WITH &&);
+            CREATE POLICY p ON documents FOR SELECT TO app_user USING (owner = current_user);
+            SELECT $tag$a;b$tag$ FROM t"
+        );
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            CREATE TYPE mood AS ENUM ('sad', 'ok');
+            CREATE SEQUENCE s START
+            WITH
+            1;
+            ALTER TABLE
+              t
+              ADD EXCLUDE USING gist (c
+                WITH
+                &&);
+            CREATE POLICY p ON documents FOR
+            SELECT
+              TO app_user USING (owner = current_user);
+            SELECT
+              $tag$a;
+            b $tag$
+            FROM
+              t"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_breaks_the_outermost_group_exceeding_max_line_width() {
+        let input = "SELECT coalesce(first_name, nickname, 'unknown') AS name, upper(concat(last_name, ', ', first_name, ' ', middle_name)) AS full_name FROM people WHERE status IN ('active', 'pending', 'suspended', 'archived')";
+        let mut options = FormatOptions {
+            max_inline_block: 100,
+            ..Default::default()
+        };
+        let expected = format(input, &QueryParams::None, &options);
+
+        // The configured layout is kept when it fits
+        options.max_line_width = Some(100);
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+
+        options.max_line_width = Some(60);
+        let expected = indoc!(
+            "
+            SELECT
+              coalesce(first_name, nickname, 'unknown') AS name,
+              upper(
+                concat(last_name, ', ', first_name, ' ', middle_name)
+              ) AS full_name
+            FROM
+              people
+            WHERE
+              status IN ('active', 'pending', 'suspended', 'archived')"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_breaks_lines_between_tokens_to_fit_max_line_width() {
+        let input = "SELECT price * quantity - discount + shipping_cost AS total, 'a string longer than the line' AS s FROM orders WHERE customer_id IN (SELECT id FROM customers WHERE region = 'EMEA')";
+        let options = FormatOptions {
+            max_inline_block: 100,
+            max_line_width: Some(30),
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              price * quantity - discount
+                + shipping_cost AS total,
+              'a string longer than the line'
+                AS s
+            FROM
+              orders
+            WHERE
+              customer_id IN (
+                SELECT
+                  id
+                FROM
+                  customers
+                WHERE
+                  region = 'EMEA'
+              )"
+        );
+        let formatted = format(input, &QueryParams::None, &options);
+        assert_eq!(formatted, expected);
+
+        // Only the string is too long to fit
+        let long_lines: Vec<&str> = formatted.lines().filter(|line| line.len() > 30).collect();
+        assert_eq!(long_lines, ["  'a string longer than the line'"]);
+    }
+
+    #[test]
+    fn it_breaks_nested_groups_outermost_first_to_fit_max_line_width() {
+        let input = "SELECT coalesce(upper(concat(first_name, ' ', last_name)), nickname) AS name FROM people";
+        let mut options = FormatOptions {
+            max_inline_block: 100,
+            max_line_width: Some(60),
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              coalesce(
+                upper(concat(first_name, ' ', last_name)),
+                nickname
+              ) AS name
+            FROM
+              people"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+
+        options.max_line_width = Some(40);
+        let expected = indoc!(
+            "
+            SELECT
+              coalesce(
+                upper(
+                  concat(first_name, ' ', last_name)
+                ),
+                nickname
+              ) AS name
+            FROM
+              people"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+
+        options.max_line_width = Some(30);
+        let expected = indoc!(
+            "
+            SELECT
+              coalesce(
+                upper(
+                  concat(
+                    first_name,
+                    ' ',
+                    last_name
+                  )
+                ),
+                nickname
+              ) AS name
+            FROM
+              people"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_breaks_between_every_token_with_a_max_line_width_of_zero_or_one() {
+        let input = "SELECT a + b AS c, f(x) FROM t WHERE a = 1";
+        let mut options = FormatOptions {
+            max_line_width: Some(0),
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              a
+                +
+                b
+                AS
+                c,
+              f(
+                x
+              )
+            FROM
+              t
+            WHERE
+              a
+                =
+                1"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+
+        options.max_line_width = Some(1);
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_keeps_tokens_longer_than_max_line_width_whole() {
+        let input = "SELECT 'a string literal longer than the width' AS s, a_column_name_longer_than_the_width, b FROM t";
+        let options = FormatOptions {
+            comma_position: CommaPosition::Leading,
+            max_line_width: Some(20),
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              'a string literal longer than the width'
+                AS s
+              , a_column_name_longer_than_the_width
+              , b
+            FROM
+              t"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_keeps_comments_within_groups_broken_to_fit_max_line_width() {
+        let input = "SELECT coalesce(a, /* fallback */ b) AS x, f(c, d) -- trailing note\n, g(e) AS y FROM t";
+        let options = FormatOptions {
+            max_inline_block: 100,
+            max_line_width: Some(20),
+            ..Default::default()
+        };
+        // Only the comment goes past the width, without breaking the block before it
+        let expected = indoc!(
+            "
+            SELECT
+              coalesce(
+                a,
+                /* fallback */
+                b
+              ) AS x,
+              f(c, d) -- trailing note
+            ,
+              g(e) AS y
+            FROM
+              t"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_keeps_inline_queries_on_a_single_line_with_max_line_width() {
+        let input = "SELECT a, b FROM t WHERE id IN (SELECT id FROM u WHERE active)";
+        let options = FormatOptions {
+            inline: true,
+            max_line_width: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(
+            format(input, &QueryParams::None, &options),
+            "SELECT a, b FROM t WHERE id IN ( SELECT id FROM u WHERE active )"
+        );
+    }
+
+    #[test]
+    fn it_fits_aligned_clauses_and_leading_commas_in_max_line_width() {
+        let input = "SELECT coalesce(upper(concat(first_name, ' ', last_name)), nickname) AS name, id FROM people WHERE id IN (SELECT person_id FROM orders WHERE total > 100) AND status = 'active' GROUP BY id, name";
+        let mut options = FormatOptions {
+            max_inline_block: 100,
+            max_line_width: Some(50),
+            clause_layout: ClauseLayout::River,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+              SELECT coalesce(
+                       upper(
+                         concat(first_name, ' ', last_name)
+                       ),
+                       nickname
+                     ) AS name,
+                     id
+                FROM people
+               WHERE id IN (
+                       SELECT person_id
+                         FROM orders
+                        WHERE total > 100
+                     )
+                 AND status = 'active'
+            GROUP BY id,
+                     name"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+
+        options.clause_layout = ClauseLayout::Indented;
+        options.comma_position = CommaPosition::Leading;
+        options.max_line_width = Some(40);
+        let expected = indoc!(
+            "
+            SELECT
+              coalesce(
+                upper(
+                  concat(first_name, ' ', last_name)
+                )
+                , nickname
+              ) AS name
+              , id
+            FROM
+              people
+            WHERE
+              id IN (
+                SELECT
+                  person_id
+                FROM
+                  orders
+                WHERE
+                  total > 100
+              )
+              AND status = 'active'
+            GROUP BY
+              id
+              , name"
+        );
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_lists_with_leading_commas() {
+        let input = "SELECT a, b, count(*) FROM t GROUP BY a, b ORDER BY a DESC, b LIMIT 5, 10; INSERT INTO t (a, b) VALUES (1, 2), (3, 4); CREATE TABLE t (id INT PRIMARY KEY, name TEXT NOT NULL, created_at TIMESTAMP DEFAULT now()); SELECT coalesce(first_argument, second_argument, third_argument, fourth_argument)";
+        let options = FormatOptions {
+            comma_position: CommaPosition::Leading,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              a
+              , b
+              , count(*)
+            FROM
+              t
+            GROUP BY
+              a
+              , b
+            ORDER BY
+              a DESC
+              , b
+            LIMIT
+              5, 10;
+            INSERT INTO
+              t (a, b)
+            VALUES
+              (1, 2)
+              , (3, 4);
+            CREATE TABLE t (
+              id INT PRIMARY KEY
+              , name TEXT NOT NULL
+              , created_at TIMESTAMP DEFAULT now()
+            );
+            SELECT
+              coalesce(
+                first_argument
+                , second_argument
+                , third_argument
+                , fourth_argument
+              )"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+
+        let options = FormatOptions {
+            inline: true,
+            ..options
+        };
+        assert_eq!(
+            format("SELECT a, b FROM t", &QueryParams::None, &options),
+            "SELECT a, b FROM t"
+        );
+    }
+
+    #[test]
+    fn it_keeps_line_comments_on_their_item_with_leading_commas() {
+        let input = indoc!(
+            "
+            SELECT
+              a, -- first
+              b -- second
+              , c,
+              -- before d
+              d
+            FROM t"
+        );
+        let options = FormatOptions {
+            comma_position: CommaPosition::Leading,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              a  -- first
+              , b -- second
+              , c
+              -- before d
+              , d
+            FROM
+              t"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_keeps_trailing_commas_on_the_last_item_with_leading_commas() {
+        let input = "CREATE TABLE t (id INT PRIMARY KEY, name TEXT NOT NULL, created_at TIMESTAMP DEFAULT now(),); CREATE TABLE u (a INT, -- note\n b INT, -- last\n); SELECT a, b, FROM t";
+        let options = FormatOptions {
+            comma_position: CommaPosition::Leading,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            CREATE TABLE t (
+              id INT PRIMARY KEY
+              , name TEXT NOT NULL
+              , created_at TIMESTAMP DEFAULT now(),
+            );
+            CREATE TABLE u (
+              a INT  -- note
+              , b INT,  -- last
+            );
+            SELECT
+              a
+              , b,
+            FROM
+              t"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_right_aligns_keywords_on_a_river() {
+        let input = "SELECT a, count(*) AS n FROM t WHERE a > 1 AND b IN (SELECT b FROM u WHERE c OR d) GROUP BY a ORDER BY n DESC; UPDATE t SET a = 1, b = 2 WHERE id = 3;";
+        let options = FormatOptions {
+            clause_layout: ClauseLayout::River,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+              SELECT a,
+                     count(*) AS n
+                FROM t
+               WHERE a > 1
+                 AND b IN (
+                       SELECT b
+                         FROM u
+                        WHERE c
+                           OR d
+                     )
+            GROUP BY a
+            ORDER BY n DESC;
+            UPDATE t
+               SET a = 1,
+                   b = 2
+             WHERE id = 3;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_computes_the_river_of_each_block() {
+        let input = "SELECT a FROM t LEFT JOIN (SELECT id FROM u WHERE v) AS w ON w.id = t.id WHERE x IN (SELECT y FROM z)";
+        let options = FormatOptions {
+            clause_layout: ClauseLayout::River,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+               SELECT a
+                 FROM t
+            LEFT JOIN (
+                        SELECT id
+                          FROM u
+                         WHERE v
+                      ) AS w ON w.id = t.id
+                WHERE x IN (
+                        SELECT y
+                          FROM z
+                      )"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_keeps_the_first_argument_on_the_keyword_line() {
+        let input = "SELECT a, b FROM t JOIN u ON u.id = t.id WHERE a > 1 AND b < 2 GROUP BY a, b; INSERT INTO t (a, b) VALUES (1, 2), (3, 4);";
+        let options = FormatOptions {
+            clause_layout: ClauseLayout::Compact,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT a,
+                   b
+            FROM t
+            JOIN u ON u.id = t.id
+            WHERE a > 1
+              AND b < 2
+            GROUP BY a,
+                     b;
+            INSERT INTO t (a, b)
+            VALUES (1, 2),
+                   (3, 4);"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_aligns_compact_blocks_on_their_own_keyword() {
+        let input = "SELECT a, CASE WHEN b THEN 1 ELSE 2 END AS c FROM (SELECT a, b FROM t WHERE x) AS s WHERE a IN (SELECT a FROM u)";
+        let options = FormatOptions {
+            clause_layout: ClauseLayout::Compact,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT a,
+                   CASE
+                     WHEN b THEN 1
+                     ELSE 2
+                   END AS c
+            FROM (
+                   SELECT a,
+                          b
+                   FROM t
+                   WHERE x
+                 ) AS s
+            WHERE a IN (
+                    SELECT a
+                    FROM u
+                  )"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_keeps_hash_operators_in_postgresql() {
+        let input = "SELECT data #> '{a}', a # b FROM t";
+        let options = FormatOptions {
+            dialect: Dialect::PostgreSql,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              data #> '{a}',
+              a # b
+            FROM
+              t"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_mysql_syntax_as_before_in_the_generic_dialect() {
+        let input = indoc!(
+            "
+            SELECT 'it''s', 1--1 FROM t STRAIGHT_JOIN u FORCE INDEX (idx_a) IGNORE INDEX FOR JOIN (idx_b) ON t.id = u.id;
+            INSERT INTO t (a, b) VALUES (1, 2) ON DUPLICATE KEY UPDATE a = VALUES(a), b = @b := 2;"
+        );
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              'it''s',
+              1 --1 FROM t STRAIGHT_JOIN u FORCE INDEX (idx_a) IGNORE INDEX FOR JOIN (idx_b) ON t.id = u.id;
+            INSERT INTO
+              t (a, b)
+            VALUES
+              (1, 2) ON DUPLICATE KEY
+            UPDATE
+              a =
+            VALUES
+            (a),
+              b = @b := 2;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_recognizes_question_numbered_placeholders() {
+        let input = "SELECT ?1, ?25, ?;";
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              ?1,
+              ?25,
+              ?;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_recognizes_question_numbered_placeholders_with_param_values() {
+        let input = "SELECT ?1, ?2, ?0;";
+        let params = vec![
+            "first".to_string(),
+            "second".to_string(),
+            "third".to_string(),
+        ];
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              second,
+              third,
+              first;"
+        );
+
+        assert_eq!(
+            format(input, &QueryParams::Indexed(params), &options),
+            expected
+        );
+
+        format("?62666666121266666612", &QueryParams::None, &options);
+    }
+
+    #[test]
+    fn it_recognizes_question_indexed_placeholders_with_param_values() {
+        let input = "SELECT ?, ?, ?;";
+        let params = vec![
+            "first".to_string(),
+            "second".to_string(),
+            "third".to_string(),
+        ];
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              first,
+              second,
+              third;"
+        );
+
+        assert_eq!(
+            format(input, &QueryParams::Indexed(params), &options),
+            expected
+        );
+    }
+
+    #[test]
+    fn it_recognizes_dollar_sign_numbered_placeholders() {
+        let input = "SELECT $1, $2;";
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              $1,
+              $2;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_recognizes_dollar_sign_alphanumeric_placeholders() {
+        let input = "SELECT $hash, $foo, $bar;";
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              $hash,
+              $foo,
+              $bar;"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_recognizes_dollar_sign_numbered_placeholders_with_param_values() {
+        let input = "SELECT $2, $3, $1, $named, $4, $alias;";
+        let params = vec![
+            "first".to_string(),
+            "second".to_string(),
+            "third".to_string(),
+            "4th".to_string(),
+        ];
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              second,
+              third,
+              first,
+              $named,
+              4th,
+              $alias;"
+        );
+
+        assert_eq!(
+            format(input, &QueryParams::Indexed(params), &options),
+            expected
+        );
+    }
+
+    #[test]
+    fn it_recognizes_dollar_sign_alphanumeric_placeholders_with_param_values() {
+        let input = "SELECT $hash, $salt, $1, $2;";
+        let params = vec![
+            ("hash".to_string(), "hash value".to_string()),
+            ("salt".to_string(), "salt value".to_string()),
+            ("1".to_string(), "number 1".to_string()),
+            ("2".to_string(), "number 2".to_string()),
+        ];
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              hash value,
+              salt value,
+              number 1,
+              number 2;"
+        );
+
+        assert_eq!(
+            format(input, &QueryParams::Named(params), &options),
+            expected
+        );
+    }
+
+    #[test]
+    fn it_recognizes_braced_placeholders_with_param_values() {
+        let input = "SELECT {a}, {b}, {c};";
+        let params = vec![
+            ("a".to_string(), "first".to_string()),
+            ("b".to_string(), "second".to_string()),
+            ("c".to_string(), "third".to_string()),
+        ];
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              first,
+              second,
+              third;"
+        );
+
+        assert_eq!(
+            format(input, &QueryParams::Named(params), &options),
+            expected
+        );
+    }
+    #[test]
+    fn it_formats_raw_sql_and_conditional_queries() {
+        let input = "SELECT {foo: &[T]}, {b.c()}, {d.e};";
+        let options = FormatOptions::default();
+        let expected = indoc!(
+            "
+            SELECT
+              {foo: &[T]},
+              {b.c()},
+              {d.e};"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
+    #[test]
+    fn it_formats_query_with_go_batch_separator() {
         let input = "SELECT 1 GO SELECT 2";
         let params = vec![
             "first".to_string(),
@@ -2122,6 +4506,24 @@ mod tests {
         assert_eq!(format(input, &QueryParams::None, &options), expected);
     }
 
+    #[test]
+    fn it_formats_tricky_line_comments_in_mysql() {
+        let input = "SELECT a#comment, here\nFROM b-- comment";
+        let options = FormatOptions {
+            dialect: Dialect::MySql,
+            ..Default::default()
+        };
+        let expected = indoc!(
+            "
+            SELECT
+              a #comment, here
+            FROM
+              b -- comment"
+        );
+
+        assert_eq!(format(input, &QueryParams::None, &options), expected);
+    }
+
     #[test]
     fn it_formats_line_comments_followed_by_semicolon() {
         let input = indoc!(
@@ -2796,4 +5198,294 @@ from
         );
         assert_eq!(format(input, &QueryParams::None, &options), expected);
     }
+
+    #[test]
+    fn it_exposes_tokens_with_spans_and_positions() {
+        let input = "SELECT a::int,\n  'é' -- note\nFROM t LEFT JOIN u";
+        let tokens = tokenize(input, &FormatOptions::default());
+
+        assert_eq!(tokens.iter().map(|t| t.text).collect::<String>(), input);
+        for token in &tokens {
+            assert_eq!(&input[token.span.clone()], token.text);
+        }
+
+        let summary: Vec<_> = tokens
+            .iter()
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| (t.kind, t.text, t.line, t.column))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (TokenKind::ReservedTopLevel, "SELECT", 1, 1),
+                (TokenKind::Word, "a", 1, 8),
+                (TokenKind::TypeSpecifier, "::", 1, 9),
+                (TokenKind::Word, "int", 1, 11),
+                (TokenKind::Operator, ",", 1, 14),
+                (TokenKind::String, "'é'", 2, 3),
+                (TokenKind::LineComment, "-- note", 2, 7),
+                (TokenKind::ReservedTopLevel, "FROM", 3, 1),
+                (TokenKind::Word, "t", 3, 6),
+                (TokenKind::ReservedNewline, "LEFT JOIN", 3, 8),
+                (TokenKind::Word, "u", 3, 18),
+            ]
+        );
+        assert_eq!(tokens.last().unwrap().span, 47..48);
+
+        let options = FormatOptions {
+            joins_as_top_level: true,
+            ..FormatOptions::default()
+        };
+        assert!(
+            tokenize(input, &options)
+                .iter()
+                .any(|t| t.kind == TokenKind::ReservedTopLevel && t.text == "LEFT JOIN")
+        );
+    }
+
+    #[test]
+    fn it_formats_valid_queries_with_try_format() {
+        let options = FormatOptions::default();
+        for input in [
+            "SELECT CASE WHEN a THEN (b) END FROM t WHERE c IN (1, 2);",
+            "BEGIN; UPDATE t SET a = 1; COMMIT;",
+            "CREATE FUNCTION abc() AS $$ DECLARE a int := 1; BEGIN SELECT 1; END $$ LANGUAGE plpgsql;",
+            "BEGIN IF a THEN SELECT 1; END IF; END;",
+        ] {
+            assert_eq!(
+                try_format(input, &QueryParams::None, &options),
+                Ok(format(input, &QueryParams::None, &options))
+            );
+        }
+    }
+
+    #[test]
+    fn it_reports_malformed_queries_with_try_format() {
+        let options = FormatOptions::default();
+        let kinds = |input: &str| {
+            try_format(input, &QueryParams::None, &options)
+                .unwrap_err()
+                .diagnostics
+                .into_iter()
+                .map(|d| (d.kind, d.span, d.line, d.column))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            kinds("SELECT a,\n  'abc FROM t"),
+            [(DiagnosticKind::UnterminatedString, 12..23, 2, 3)]
+        );
+        assert_eq!(
+            kinds("SELECT a /* note"),
+            [(DiagnosticKind::UnterminatedComment, 9..16, 1, 10)]
+        );
+        assert_eq!(
+            kinds("SELECT a) FROM t END"),
+            [
+                (DiagnosticKind::UnmatchedClose, 8..9, 1, 9),
+                (DiagnosticKind::UnmatchedClose, 17..20, 1, 18)
+            ]
+        );
+        assert_eq!(
+            kinds("SELECT (a, CASE WHEN b THEN c FROM t;\nSELECT 1"),
+            [
+                (DiagnosticKind::UnclosedBlock, 7..8, 1, 8),
+                (DiagnosticKind::UnclosedBlock, 11..15, 1, 12)
+            ]
+        );
+
+        let error = try_format("SELECT (a", &QueryParams::None, &options).unwrap_err();
+        assert_eq!(error.to_string(), "1:8: unclosed block");
+    }
+
+    #[test]
+    fn it_ends_statements_on_dialect_terminators_with_try_format() {
+        let kinds = |input: &str, dialect| {
+            let options = FormatOptions {
+                dialect,
+                ..Default::default()
+            };
+            try_format(input, &QueryParams::None, &options)
+                .unwrap_err()
+                .diagnostics
+                .into_iter()
+                .map(|d| (d.kind, d.span))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            kinds("DELIMITER //\nSELECT (1 //\nSELECT 2) //", Dialect::MySql),
+            [
+                (DiagnosticKind::UnclosedBlock, 20..21),
+                (DiagnosticKind::UnmatchedClose, 34..35)
+            ]
+        );
+        assert_eq!(
+            kinds("SELECT (1\n/\nSELECT 2)\n/", Dialect::Oracle),
+            [
+                (DiagnosticKind::UnclosedBlock, 7..8),
+                (DiagnosticKind::UnmatchedClose, 20..21)
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reports_unterminated_dialect_strings_with_try_format() {
+        let kinds = |input: &str, dialect| {
+            let options = FormatOptions {
+                dialect,
+                ..Default::default()
+            };
+            try_format(input, &QueryParams::None, &options)
+                .unwrap_err()
+                .diagnostics
+                .into_iter()
+                .map(|d| (d.kind, d.span))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            kinds(
+                "CREATE FUNCTION f() RETURNS int AS $$ SELECT 1",
+                Dialect::PostgreSql
+            ),
+            [(DiagnosticKind::UnterminatedString, 35..46)]
+        );
+        assert_eq!(
+            kinds("SELECT $t$ a $$ b;", Dialect::PostgreSql),
+            [(DiagnosticKind::UnterminatedString, 7..18)]
+        );
+        assert_eq!(
+            kinds("SELECT q'[it's] FROM t", Dialect::Oracle),
+            [(DiagnosticKind::UnterminatedString, 7..22)]
+        );
+    }
+
+    #[test]
+    fn it_formats_only_the_statements_in_a_range() {
+        let input = indoc!(
+            "
+            SELECT a,   b FROM t;
+            -- indented
+                SELECT c,  d FROM u WHERE c = 1;
+                SELECT e FROM v;"
+        );
+        let options = FormatOptions::default();
+        let start = input.find("c,").unwrap();
+
+        let edits = format_range(input, start..start + 1, &QueryParams::None, &options);
+        let expected = indoc!(
+            "
+            SELECT a,   b FROM t;
+            -- indented
+                SELECT
+                  c,
+                  d
+                FROM
+                  u
+                WHERE
+                  c = 1;
+                SELECT e FROM v;"
+        );
+        assert_eq!(edits.len(), 1);
+        assert_eq!(TextEdit::apply(input, &edits), expected);
+
+        let edits = format_lines(input, 1..=1, &QueryParams::None, &options);
+        assert_eq!(
+            edits,
+            [TextEdit {
+                range: 0..21,
+                new_text: "SELECT\n  a,\n  b\nFROM\n  t;".to_string(),
+            }]
+        );
+        assert_eq!(
+            format_lines(input, 4..=4, &QueryParams::None, &options)[0].new_text,
+            "SELECT\n      e\n    FROM\n      v;"
+        );
+        assert!(format_lines(input, 2..=2, &QueryParams::None, &options).is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_serializes_options_with_serde() {
+        let options = FormatOptions {
+            indent: Indent::Tabs,
+            uppercase: Some(true),
+            ignore_case_convert: Some(vec!["from"]),
+            max_inline_arguments: Some(40),
+            dialect: Dialect::PostgreSql,
+            ..FormatOptions::default()
+        };
+        let json = serde_json::to_value(&options).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "indent": "tabs",
+                "uppercase": true,
+                "lines_between_queries": 1,
+                "ignore_case_convert": ["from"],
+                "inline": false,
+                "max_inline_block": 50,
+                "max_inline_arguments": 40,
+                "max_inline_top_level": null,
+                "max_line_width": null,
+                "comma_position": "trailing",
+                "clause_layout": "indented",
+                "joins_as_top_level": false,
+                "dialect": "postgresql",
+                "format_function_bodies": true
+            })
+        );
+
+        let source = r#"{"indent": 4, "ignore_case_convert": ["where"], "dialect": "mssql"}"#;
+        let options: FormatOptions = serde_json::from_str(source).unwrap();
+        assert_eq!(options.indent, Indent::Spaces(4));
+        assert_eq!(options.ignore_case_convert, Some(vec!["where"]));
+        assert_eq!(options.dialect, Dialect::SQLServer);
+        assert_eq!(options.max_inline_block, 50);
+
+        assert!(serde_json::from_str::<FormatOptions>(r#"{"indent": 256}"#).is_err());
+        assert!(serde_json::from_str::<FormatOptions>(r#"{"indnet": 4}"#).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_serializes_owned_config_and_params_with_serde() {
+        let options = FormatOptions {
+            ignore_case_convert: Some(vec!["from"]),
+            ..FormatOptions::default()
+        };
+        let config = Config::from(&options);
+        let json = serde_json::to_string(&config).unwrap();
+        // Owned strings don't need to borrow from the input
+        let reader = std::io::Cursor::new(json.into_bytes());
+        let config: Config = serde_json::from_reader(reader).unwrap();
+        assert_eq!(config.ignore_case_convert, Some(vec!["from".to_string()]));
+        assert_eq!(
+            config.format_options().ignore_case_convert,
+            Some(vec!["from"])
+        );
+
+        let config: Config = serde_json::from_str(r#"{"uppercase": false}"#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            r#"{"uppercase":false}"#
+        );
+
+        for (params, json) in [
+            (
+                QueryParams::Named(vec![("name".to_string(), "value".to_string())]),
+                r#"{"named":[["name","value"]]}"#,
+            ),
+            (
+                QueryParams::Indexed(vec!["value".to_string()]),
+                r#"{"indexed":["value"]}"#,
+            ),
+            (QueryParams::None, r#""none""#),
+        ] {
+            assert_eq!(serde_json::to_string(&params).unwrap(), json);
+            let roundtrip: QueryParams = serde_json::from_str(json).unwrap();
+            assert_eq!(format!("{roundtrip:?}"), format!("{params:?}"));
+        }
+    }
 }
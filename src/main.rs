@@ -1,31 +1,775 @@
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
 use std::{env, fs, process};
 
-fn run() -> Result<(), String> {
-    let mut args = env::args();
-    let program = args.next().unwrap_or_else(|| String::from("sqlformat"));
+use sqlformat::{ClauseLayout, CommaPosition, Config, Dialect, FormatOptions, Indent, QueryParams};
 
-    let filename = match (args.next(), args.next()) {
-        (Some(filename), None) => filename,
-        _ => return Err(format!("Usage: {program} <filename>")),
+const USAGE: &str = "Usage: sqlformat [OPTIONS] [PATH]...";
+
+const HELP: &str = "\
+Formats whitespace in SQL files to make them easier to read.
+
+Files are formatted in place. Directories are searched recursively for files
+with one of the given extensions, skipping anything matched by a gitignore-style
+`.sqlformatignore` file, and quoted glob patterns such as `'migrations/**/*.sql'`
+are expanded the same way. When the path is `-` or omitted while input is piped
+in, SQL is read from stdin and written to stdout instead.
+
+Options are read from the closest `sqlformat.toml` found by walking up from each
+file (or from the current directory for stdin), command line flags take
+precedence over it.
+
+With `--check` nothing is written: inputs that would change are listed and
+the exit code is non-zero.
+
+Options:
+      --indent <N>                   Indent with N spaces [default: 2]
+      --tabs                         Indent with tabs instead of spaces
+      --uppercase                    Convert reserved keywords to UPPERCASE
+      --lowercase                    Convert reserved keywords to lowercase
+      --preserve-case                Leave the case of reserved keywords as written
+      --lines-between-queries <N>    Number of line breaks after a query [default: 1]
+      --inline                       Keep each query on a single line
+      --no-inline                    Break queries over multiple lines
+      --max-inline-block <N>         Maximum length of an inline block [default: 50]
+      --max-inline-arguments <N>     Maximum length of inline arguments
+      --max-inline-top-level <N>     Inline top level arguments if they fit in N characters
+      --max-line-width <N>           Break lines longer than N characters where possible
+      --leading-commas               Start the lines of lists broken over lines with their comma
+      --trailing-commas              End the lines of lists broken over lines with their comma
+      --clause-layout <LAYOUT>       Layout of the arguments of top level keywords: indented,
+                                     river or compact [default: indented]
+      --joins-as-top-level           Treat any JOIN as a top level keyword
+      --no-joins-as-top-level        Indent JOINs below the FROM keyword
+      --dialect <DIALECT>            SQL dialect: generic, postgresql, sqlserver, mysql, sqlite,
+                                     oracle, bigquery, snowflake, clickhouse,
+                                     duckdb, spark, trino or redshift
+                                     [default: generic]
+      --format-function-bodies       Format the PL/pgSQL and SQL bodies of PostgreSQL functions
+                                     [default]
+      --no-format-function-bodies    Keep the bodies of PostgreSQL functions as written
+      --ignore-case-convert <WORDS>  Comma separated words to leave untouched by case conversion
+      --check                        Report unformatted input instead of rewriting it
+      --diff                         Like --check, but print a unified diff of the changes
+      --extensions <EXTS>            Comma separated extensions to search directories for [default: sql]
+      --config <PATH>                Use this configuration file instead of discovering sqlformat.toml
+  -h, --help                         Print help
+  -V, --version                      Print version";
+
+/// Command line arguments, every option is left unset unless given explicitly.
+#[derive(Debug, Default)]
+struct Args {
+    indent: Option<Indent>,
+    /// `Some(None)` when `--preserve-case` resets a configured case conversion
+    uppercase: Option<Option<bool>>,
+    lines_between_queries: Option<u8>,
+    inline: Option<bool>,
+    max_inline_block: Option<usize>,
+    max_inline_arguments: Option<usize>,
+    max_inline_top_level: Option<usize>,
+    max_line_width: Option<usize>,
+    comma_position: Option<CommaPosition>,
+    clause_layout: Option<ClauseLayout>,
+    joins_as_top_level: Option<bool>,
+    dialect: Option<Dialect>,
+    format_function_bodies: Option<bool>,
+    ignore_case_convert: Vec<String>,
+    check: bool,
+    diff: bool,
+    extensions: Vec<String>,
+    config: Option<String>,
+    paths: Vec<String>,
+}
+
+enum Command {
+    Format(Args),
+    Help,
+    Version,
+}
+
+impl Args {
+    /// Layers the command line options over the given configuration
+    fn format_options<'a>(&'a self, config: &'a Config) -> FormatOptions<'a> {
+        let base = config.format_options();
+        FormatOptions {
+            indent: self.indent.unwrap_or(base.indent),
+            uppercase: self.uppercase.unwrap_or(base.uppercase),
+            lines_between_queries: self
+                .lines_between_queries
+                .unwrap_or(base.lines_between_queries),
+            ignore_case_convert: if self.ignore_case_convert.is_empty() {
+                base.ignore_case_convert
+            } else {
+                Some(
+                    self.ignore_case_convert
+                        .iter()
+                        .map(String::as_str)
+                        .collect(),
+                )
+            },
+            inline: self.inline.unwrap_or(base.inline),
+            max_inline_block: self.max_inline_block.unwrap_or(base.max_inline_block),
+            max_inline_arguments: self.max_inline_arguments.or(base.max_inline_arguments),
+            max_inline_top_level: self.max_inline_top_level.or(base.max_inline_top_level),
+            max_line_width: self.max_line_width.or(base.max_line_width),
+            comma_position: self.comma_position.unwrap_or(base.comma_position),
+            clause_layout: self.clause_layout.unwrap_or(base.clause_layout),
+            joins_as_top_level: self.joins_as_top_level.unwrap_or(base.joins_as_top_level),
+            dialect: self.dialect.unwrap_or(base.dialect),
+            format_function_bodies: self
+                .format_function_bodies
+                .unwrap_or(base.format_function_bodies),
+        }
+    }
+}
+
+/// Loads the configuration applying to each input, reading every file only once
+struct Configs {
+    explicit: Option<Config>,
+    discovered: HashMap<PathBuf, Config>,
+}
+
+impl Configs {
+    fn new(explicit: Option<&str>) -> Result<Self, String> {
+        Ok(Configs {
+            explicit: explicit
+                .map(|path| Config::from_file(Path::new(path)))
+                .transpose()
+                .map_err(|err| format!("Error reading configuration {err}"))?,
+            discovered: HashMap::new(),
+        })
+    }
+
+    /// The `--config` file if given, otherwise the closest `sqlformat.toml` to `start`
+    fn get(&mut self, start: &Path) -> Result<Config, String> {
+        if let Some(config) = &self.explicit {
+            return Ok(config.clone());
+        }
+        let Some(path) = Config::find(start) else {
+            return Ok(Config::default());
+        };
+        if let Some(config) = self.discovered.get(&path) {
+            return Ok(config.clone());
+        }
+        let config =
+            Config::from_file(&path).map_err(|err| format!("Error reading configuration {err}"))?;
+        self.discovered.insert(path, config.clone());
+        Ok(config)
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut parsed = Args::default();
+
+    while let Some(arg) = args.next() {
+        // Accept both `--flag value` and `--flag=value`
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || -> Result<String, String> {
+            inline_value
+                .map(str::to_string)
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("Missing value for '{flag}'"))
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--indent" => parsed.indent = Some(Indent::Spaces(parse_number(&flag, &value()?)?)),
+            "--tabs" => parsed.indent = Some(Indent::Tabs),
+            "--uppercase" => parsed.uppercase = Some(Some(true)),
+            "--lowercase" => parsed.uppercase = Some(Some(false)),
+            "--preserve-case" => parsed.uppercase = Some(None),
+            "--lines-between-queries" => {
+                parsed.lines_between_queries = Some(parse_number(&flag, &value()?)?)
+            }
+            "--inline" => parsed.inline = Some(true),
+            "--no-inline" => parsed.inline = Some(false),
+            "--max-inline-block" => parsed.max_inline_block = Some(parse_number(&flag, &value()?)?),
+            "--max-inline-arguments" => {
+                parsed.max_inline_arguments = Some(parse_number(&flag, &value()?)?)
+            }
+            "--max-inline-top-level" => {
+                parsed.max_inline_top_level = Some(parse_number(&flag, &value()?)?)
+            }
+            "--max-line-width" => parsed.max_line_width = Some(parse_number(&flag, &value()?)?),
+            "--leading-commas" => parsed.comma_position = Some(CommaPosition::Leading),
+            "--trailing-commas" => parsed.comma_position = Some(CommaPosition::Trailing),
+            "--clause-layout" => parsed.clause_layout = Some(parse_clause_layout(&value()?)?),
+            "--joins-as-top-level" => parsed.joins_as_top_level = Some(true),
+            "--no-joins-as-top-level" => parsed.joins_as_top_level = Some(false),
+            "--format-function-bodies" => parsed.format_function_bodies = Some(true),
+            "--no-format-function-bodies" => parsed.format_function_bodies = Some(false),
+            "--check" => parsed.check = true,
+            "--diff" => {
+                parsed.check = true;
+                parsed.diff = true;
+            }
+            "--dialect" => parsed.dialect = Some(parse_dialect(&value()?)?),
+            "--config" => parsed.config = Some(value()?),
+            "--ignore-case-convert" => parsed.ignore_case_convert.extend(
+                value()?
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_string),
+            ),
+            "--extensions" => parsed.extensions.extend(
+                value()?
+                    .split(',')
+                    .map(|ext| ext.trim().trim_start_matches('.'))
+                    .filter(|ext| !ext.is_empty())
+                    .map(str::to_string),
+            ),
+            _ if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("Unknown option '{flag}'"));
+            }
+            _ => parsed.paths.push(arg),
+        }
+    }
+
+    Ok(Command::Format(parsed))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value '{value}' for '{flag}': expected a number"))
+}
+
+fn parse_dialect(value: &str) -> Result<Dialect, String> {
+    value
+        .parse()
+        .map_err(|err| format!("Invalid value '{value}' for '--dialect': {err}"))
+}
+
+fn parse_clause_layout(value: &str) -> Result<ClauseLayout, String> {
+    match value {
+        "indented" => Ok(ClauseLayout::Indented),
+        "river" => Ok(ClauseLayout::River),
+        "compact" => Ok(ClauseLayout::Compact),
+        _ => Err(format!(
+            "Invalid value '{value}' for '--clause-layout': expected indented, river or compact"
+        )),
+    }
+}
+
+/// Formats the input, returning `Ok(false)` when a file failed or `--check` found
+/// unformatted input
+fn run() -> Result<bool, String> {
+    let args = match parse_args(env::args().skip(1))
+        .map_err(|err| format!("{err}\n\n{USAGE}\n\nFor more information, try '--help'."))?
+    {
+        Command::Help => {
+            println!("{USAGE}\n\n{HELP}");
+            return Ok(true);
+        }
+        Command::Version => {
+            println!("sqlformat {}", env!("CARGO_PKG_VERSION"));
+            return Ok(true);
+        }
+        Command::Format(args) => args,
+    };
+
+    let mut configs = Configs::new(args.config.as_deref())?;
+    let cwd =
+        env::current_dir().map_err(|err| format!("Error reading current directory: {err}"))?;
+
+    match args.paths.as_slice() {
+        [] if io::stdin().is_terminal() => {
+            return Err(format!("{USAGE}\n\nFor more information, try '--help'."));
+        }
+        [] => return format_stdin(&args, &configs.get(&cwd)?),
+        [path] if path == "-" => return format_stdin(&args, &configs.get(&cwd)?),
+        paths if paths.iter().any(|path| path == "-") => {
+            return Err("'-' cannot be combined with other paths".to_string());
+        }
+        _ => {}
+    }
+
+    let extensions: Vec<&str> = if args.extensions.is_empty() {
+        vec!["sql"]
+    } else {
+        args.extensions.iter().map(String::as_str).collect()
     };
+    let mut files = Vec::new();
+    let mut failed = 0;
+    for path in &args.paths {
+        if let Err(err) = collect_files(Path::new(path), &cwd, &extensions, &mut files) {
+            eprintln!("{err}");
+            failed += 1;
+        }
+    }
+
+    let (mut changed, mut unchanged) = (0, 0);
+    for file in &files {
+        match configs
+            .get(file)
+            .and_then(|config| format_file(file, &args, &config))
+        {
+            Ok(true) => {
+                changed += 1;
+                if !args.check {
+                    eprintln!("changed    {}", file.display());
+                }
+            }
+            Ok(false) => {
+                unchanged += 1;
+                if !args.check {
+                    eprintln!("unchanged  {}", file.display());
+                }
+            }
+            Err(err) => {
+                failed += 1;
+                eprintln!("{err}");
+            }
+        }
+    }
+
+    let total = changed + unchanged + failed;
+    if total > 1 {
+        eprintln!("{total} files: {changed} changed, {unchanged} unchanged, {failed} failed");
+    }
+
+    Ok(failed == 0 && !(args.check && changed > 0))
+}
+
+fn format_stdin(args: &Args, config: &Config) -> Result<bool, String> {
+    let input = read_stdin()?;
+    let formatted = sqlformat::format(&input, &QueryParams::None, &args.format_options(config));
+
+    if args.check {
+        report_unformatted(args, "<stdin>", &input, &formatted);
+        return Ok(formatted == input);
+    }
+
+    io::stdout()
+        .lock()
+        .write_all(formatted.as_bytes())
+        .map_err(|err| format!("Error writing stdout: {err}"))?;
+
+    Ok(true)
+}
+
+/// Formats a single file, returning whether its contents changed
+fn format_file(path: &Path, args: &Args, config: &Config) -> Result<bool, String> {
+    let input = fs::read_to_string(path)
+        .map_err(|err| format!("Error reading '{}': {err}", path.display()))?;
+    let formatted = sqlformat::format(&input, &QueryParams::None, &args.format_options(config));
+
+    if formatted == input {
+        return Ok(false);
+    }
+
+    if args.check {
+        report_unformatted(args, &path.display().to_string(), &input, &formatted);
+    } else {
+        fs::write(path, formatted)
+            .map_err(|err| format!("Error writing '{}': {err}", path.display()))?;
+    }
 
-    let input = fs::read_to_string(&filename)
-        .map_err(|err| format!("Error reading '{filename}': {err}"))?;
+    Ok(true)
+}
+
+fn report_unformatted(args: &Args, name: &str, input: &str, formatted: &str) {
+    if formatted == input {
+        return;
+    }
+    if args.diff {
+        print!("{}", unified_diff(input, formatted, name));
+    } else {
+        println!("{name}");
+    }
+}
+
+/// Expands a command line path into the files to format.
+///
+/// Directories are walked recursively for files with one of the given extensions and
+/// paths that don't exist are treated as glob patterns. Both honor `.sqlformatignore`
+/// files, files named explicitly are always formatted.
+fn collect_files(
+    path: &Path,
+    cwd: &Path,
+    extensions: &[&str],
+    files: &mut Vec<PathBuf>,
+) -> Result<(), String> {
+    let path_str = path.to_string_lossy();
+    if path.is_dir() {
+        let ignores = IgnoreFile::load_ancestors(&cwd.join(path), cwd)?;
+        walk_dir(path, cwd, ignores, &mut |file| {
+            if file
+                .extension()
+                .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+            {
+                files.push(file.to_path_buf());
+            }
+        })
+    } else if !path.exists() && path_str.contains(['*', '?', '[']) {
+        // Walk from the longest directory prefix without glob characters
+        let pattern = path_str.replace('\\', "/");
+        let literal_len = pattern
+            .find(['*', '?', '['])
+            .and_then(|pos| pattern[..pos].rfind('/').map(|slash| slash + 1))
+            .unwrap_or(0);
+        let root = match &pattern[..literal_len] {
+            "" => Path::new("."),
+            prefix => Path::new(prefix),
+        };
+        let glob = &pattern[literal_len..];
+        let before = files.len();
+
+        if root.is_dir() {
+            let ignores = IgnoreFile::load_ancestors(&cwd.join(root), cwd)?;
+            walk_dir(root, cwd, ignores, &mut |file| {
+                let relative = file.strip_prefix(root).unwrap_or(file);
+                if glob_match(glob, &relative.to_string_lossy().replace('\\', "/")) {
+                    files.push(file.to_path_buf());
+                }
+            })?;
+        }
+        if files.len() == before {
+            return Err(format!(
+                "Error reading '{path_str}': no files match the pattern"
+            ));
+        }
+        Ok(())
+    } else {
+        files.push(path.to_path_buf());
+        Ok(())
+    }
+}
 
-    let formatted = sqlformat::format(
-        &input,
-        &sqlformat::QueryParams::None,
-        &sqlformat::FormatOptions::default(),
-    );
+/// Recursively visits every file below `dir` in a stable order, skipping ignored entries
+fn walk_dir(
+    dir: &Path,
+    cwd: &Path,
+    mut ignores: Vec<IgnoreFile>,
+    visit: &mut dyn FnMut(&Path),
+) -> Result<(), String> {
+    let absolute = cwd.join(dir);
+    if !ignores.iter().any(|ignore| ignore.base == absolute) {
+        if let Some(ignore) = IgnoreFile::load(&absolute)? {
+            ignores.push(ignore);
+        }
+    }
 
-    fs::write(&filename, formatted).map_err(|err| format!("Error writing '{filename}': {err}"))?;
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| format!("Error reading '{}': {err}", dir.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        // Symlinked directories are not followed to avoid cycles
+        let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+        let absolute = cwd.join(&path);
+        if ignores
+            .iter()
+            .rev()
+            .find_map(|ignore| ignore.is_ignored(&absolute, is_dir))
+            .unwrap_or(false)
+        {
+            continue;
+        }
+        if is_dir {
+            walk_dir(&path, cwd, ignores.clone(), visit)?;
+        } else if path.is_file() {
+            visit(&path);
+        }
+    }
 
     Ok(())
 }
 
+const IGNORE_FILE: &str = ".sqlformatignore";
+
+/// The patterns of a `.sqlformatignore` file, relative to the directory containing it
+#[derive(Debug, Clone)]
+struct IgnoreFile {
+    base: PathBuf,
+    patterns: Vec<IgnorePattern>,
+}
+
+#[derive(Debug, Clone)]
+struct IgnorePattern {
+    glob: String,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl IgnoreFile {
+    fn load(dir: &Path) -> Result<Option<Self>, String> {
+        let path = dir.join(IGNORE_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&path)
+            .map_err(|err| format!("Error reading '{}': {err}", path.display()))?;
+
+        Ok(Some(IgnoreFile {
+            base: dir.to_path_buf(),
+            patterns: contents.lines().filter_map(IgnorePattern::parse).collect(),
+        }))
+    }
+
+    /// Loads the ignore files of every directory from `cwd` down to `dir`
+    fn load_ancestors(dir: &Path, cwd: &Path) -> Result<Vec<Self>, String> {
+        let mut ignores = Vec::new();
+        for ancestor in dir.ancestors().filter(|ancestor| ancestor.starts_with(cwd)) {
+            ignores.extend(Self::load(ancestor)?);
+        }
+        ignores.reverse();
+        Ok(ignores)
+    }
+
+    /// Returns whether the last matching pattern ignores the path, if any matches
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?;
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let name = relative.rsplit('/').next().unwrap_or(&relative);
+
+        self.patterns.iter().rev().find_map(|pattern| {
+            let matched = (!pattern.dir_only || is_dir)
+                && if pattern.anchored {
+                    glob_match(&pattern.glob, &relative)
+                } else {
+                    glob_match(&pattern.glob, name)
+                };
+            matched.then_some(!pattern.negated)
+        })
+    }
+}
+
+impl IgnorePattern {
+    /// Parses a gitignore-style line
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(line) => (true, line),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        // A slash anywhere but at the end anchors the pattern to the ignore file directory
+        let anchored = line.contains('/');
+        let glob = line.strip_prefix('/').unwrap_or(line);
+
+        (!glob.is_empty()).then(|| IgnorePattern {
+            glob: glob.to_string(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+}
+
+/// Matches a `/` separated path against a glob supporting `*`, `**`, `?`, `[...]` and
+/// `\` escapes
+fn glob_match(glob: &str, path: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    glob_match_chars(&glob, &path)
+}
+
+fn glob_match_chars(glob: &[char], path: &[char]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // Zero or more directories
+            glob_match_chars(rest, path)
+                || (0..path.len())
+                    .filter(|&i| path[i] == '/')
+                    .any(|i| glob_match_chars(rest, &path[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| glob_match_chars(rest, &path[i..])),
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != '/')
+            .any(|i| glob_match_chars(rest, &path[i..])),
+        ['?', rest @ ..] => {
+            path.first().is_some_and(|&c| c != '/') && glob_match_chars(rest, &path[1..])
+        }
+        ['[', class @ ..] => {
+            let Some(end) = class.iter().skip(1).position(|&c| c == ']').map(|i| i + 1) else {
+                return path.first() == Some(&'[') && glob_match_chars(class, &path[1..]);
+            };
+            let (negated, items) = match &class[..end] {
+                ['!' | '^', items @ ..] => (true, items),
+                items => (false, items),
+            };
+            let Some(&c) = path.first() else {
+                return false;
+            };
+            let mut matched = false;
+            let mut i = 0;
+            while i < items.len() {
+                if i + 2 < items.len() && items[i + 1] == '-' {
+                    matched |= (items[i]..=items[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    matched |= items[i] == c;
+                    i += 1;
+                }
+            }
+            c != '/' && matched != negated && glob_match_chars(&class[end + 1..], &path[1..])
+        }
+        ['\\', c, rest @ ..] | [c, rest @ ..] => {
+            path.first() == Some(c) && glob_match_chars(rest, &path[1..])
+        }
+    }
+}
+
+fn read_stdin() -> Result<String, String> {
+    let mut input = Vec::new();
+    io::stdin()
+        .read_to_end(&mut input)
+        .map_err(|err| format!("Error reading stdin: {err}"))?;
+    String::from_utf8(input)
+        .map_err(|err| format!("Error reading stdin: invalid UTF-8 input ({err})"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Computes a line based edit script using the longest common subsequence
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Edit, &'a str)> {
+    // Only the differing middle part needs the quadratic table
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    // lcs[i * width + j] is the length of the LCS of a[i..] and b[j..]
+    let width = b.len() + 1;
+    let mut lcs = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = if a[i] == b[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut edits: Vec<_> = old[..prefix].iter().map(|l| (Edit::Equal, *l)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            edits.push((Edit::Equal, a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len()
+            && (j == b.len() || lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            edits.push((Edit::Delete, a[i]));
+            i += 1;
+        } else {
+            edits.push((Edit::Insert, b[j]));
+            j += 1;
+        }
+    }
+    edits.extend(old[old.len() - suffix..].iter().map(|l| (Edit::Equal, *l)));
+    edits
+}
+
+/// Renders a unified diff with three lines of context around each change
+fn unified_diff(old: &str, new: &str, name: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = diff_lines(&old_lines, &new_lines);
+
+    // Line numbers in the old and new text right before each edit
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for (edit, _) in &edits {
+        positions.push((old_pos, new_pos));
+        match edit {
+            Edit::Equal => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Edit::Delete => old_pos += 1,
+            Edit::Insert => new_pos += 1,
+        }
+    }
+    positions.push((old_pos, new_pos));
+
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, (edit, _))| *edit != Edit::Equal)
+        .map(|(index, _)| index)
+        .collect();
+
+    let mut diff = format!("--- {name}\n+++ {name}\n");
+    let mut k = 0;
+    while k < changes.len() {
+        let first = changes[k];
+        let mut last = first;
+        k += 1;
+        // Merge changes whose context would overlap into the same hunk
+        while k < changes.len() && changes[k] - last <= 2 * CONTEXT + 1 {
+            last = changes[k];
+            k += 1;
+        }
+
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(edits.len());
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        let range = |start: usize, len: usize| {
+            // An empty range refers to the line right before it
+            format!("{},{len}", if len == 0 { start } else { start + 1 })
+        };
+        diff.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_end - old_start),
+            range(new_start, new_end - new_start)
+        ));
+
+        for (edit, line) in &edits[start..end] {
+            diff.push(match edit {
+                Edit::Equal => ' ',
+                Edit::Delete => '-',
+                Edit::Insert => '+',
+            });
+            diff.push_str(line);
+            if !line.ends_with('\n') {
+                diff.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    diff
+}
+
 fn main() {
-    if let Err(err) = run() {
-        eprintln!("{err}");
-        process::exit(1);
+    match run() {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(1);
+        }
     }
 }
@@ -1,6 +1,7 @@
 use crate::QueryParams;
 use crate::tokenizer::Token;
 
+#[derive(Clone)]
 pub(crate) struct Params<'a> {
     index: usize,
     params: &'a QueryParams,
@@ -0,0 +1,149 @@
+use std::ops::{Range, RangeInclusive};
+
+use crate::formatter::{self, check_fmt_off};
+use crate::tokenizer::{self, Token, TokenKind};
+use crate::{FormatOptions, QueryParams};
+
+/// A replacement of part of a document, as returned by [`format_range`](crate::format_range)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// The byte range of the replaced text in the original document
+    pub range: Range<usize>,
+    pub new_text: String,
+}
+
+impl TextEdit {
+    /// Applies `edits` to `document`. The edits must not overlap, as is the case for the
+    /// edits returned by [`format_range`](crate::format_range).
+    pub fn apply(document: &str, edits: &[TextEdit]) -> String {
+        let mut edits: Vec<&TextEdit> = edits.iter().collect();
+        edits.sort_by_key(|edit| edit.range.start);
+
+        let mut result = String::with_capacity(document.len());
+        let mut offset = 0;
+        for edit in edits {
+            result.push_str(&document[offset..edit.range.start]);
+            result.push_str(&edit.new_text);
+            offset = edit.range.end;
+        }
+        result.push_str(&document[offset..]);
+        result
+    }
+}
+
+pub(crate) fn format_range(
+    document: &str,
+    range: Range<usize>,
+    params: &QueryParams,
+    options: &FormatOptions,
+) -> Vec<TextEdit> {
+    let named_placeholders = matches!(params, QueryParams::Named(_));
+    let tokens = tokenizer::tokenize(document, named_placeholders, options);
+
+    statements(&tokens)
+        .into_iter()
+        .filter(|statement| overlaps(&statement.span, &range))
+        .filter_map(|statement| {
+            let original = &document[statement.span.clone()];
+            let formatted = formatter::format(&tokens[statement.tokens], params, options);
+            let new_text = indent_lines(&formatted, base_indentation(document, &statement.span));
+            (new_text != original).then_some(TextEdit {
+                range: statement.span,
+                new_text,
+            })
+        })
+        .collect()
+}
+
+/// Converts a 1-based inclusive range of lines to the byte range covering their content
+pub(crate) fn line_range(document: &str, lines: RangeInclusive<usize>) -> Range<usize> {
+    let mut line_starts = std::iter::once(0)
+        .chain(document.match_indices('\n').map(|(i, _)| i + 1))
+        .chain(std::iter::once(document.len() + 1));
+
+    let first = (*lines.start()).max(1);
+    let last = (*lines.end()).max(first);
+    let start = line_starts.nth(first - 1).unwrap_or(document.len() + 1);
+    let end = line_starts.nth(last - first).unwrap_or(document.len() + 1);
+    start.min(document.len())..end.saturating_sub(1).min(document.len())
+}
+
+struct Statement {
+    /// The byte range from the first token of the statement to its `;`, or its last token
+    span: Range<usize>,
+    /// The indices of the tokens in `span`
+    tokens: Range<usize>,
+}
+
+/// Splits the tokens on query separators, skipping the statements in `fmt: off` regions.
+/// Statements start at their first token that is neither whitespace nor a comment.
+fn statements(tokens: &[Token<'_>]) -> Vec<Statement> {
+    let mut statements = Vec::new();
+    let mut current: Option<Statement> = None;
+    let mut is_fmt_enabled = true;
+    let mut offset = 0;
+
+    for (index, token) in tokens.iter().enumerate() {
+        let span = offset..offset + token.value.len();
+        offset = span.end;
+
+        if token.kind == TokenKind::Whitespace {
+            continue;
+        }
+        let fmt_switch = matches!(token.kind, TokenKind::LineComment | TokenKind::BlockComment)
+            .then(|| check_fmt_off(token.value))
+            .flatten();
+        if let Some(is_fmt_off) = fmt_switch {
+            is_fmt_enabled = !is_fmt_off;
+        }
+        // Comments between statements are left where they are
+        if current.is_none()
+            && (!is_fmt_enabled
+                || matches!(token.kind, TokenKind::LineComment | TokenKind::BlockComment))
+        {
+            continue;
+        }
+
+        let statement = current.get_or_insert(Statement {
+            span: span.clone(),
+            tokens: index..index,
+        });
+        statement.span.end = span.end;
+        statement.tokens.end = index + 1;
+        if token.kind == TokenKind::Operator && token.alias == ";" {
+            statements.extend(current.take());
+        }
+    }
+    statements.extend(current);
+    statements
+}
+
+fn overlaps(statement: &Range<usize>, range: &Range<usize>) -> bool {
+    if range.is_empty() {
+        // An empty range is a cursor position, which touches the statement at either end
+        statement.start <= range.start && range.start <= statement.end
+    } else {
+        statement.start < range.end && range.start < statement.end
+    }
+}
+
+/// The leading whitespace of the line the statement starts on
+fn base_indentation<'a>(document: &'a str, statement: &Range<usize>) -> &'a str {
+    let line_start = document[..statement.start].rfind('\n').map_or(0, |i| i + 1);
+    let line = &document[line_start..statement.start];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+fn indent_lines(formatted: &str, indentation: &str) -> String {
+    let mut result = String::with_capacity(formatted.len());
+    for (i, line) in formatted.split('\n').enumerate() {
+        if i > 0 {
+            result.push('\n');
+            if !line.is_empty() {
+                result.push_str(indentation);
+            }
+        }
+        result.push_str(line);
+    }
+    result
+}
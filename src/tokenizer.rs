@@ -1,19 +1,39 @@
 use std::borrow::Cow;
 use unicode_categories::UnicodeCategories;
 use winnow::Result;
-use winnow::ascii::{Caseless, digit0, digit1, till_line_ending};
-use winnow::combinator::{alt, delimited, dispatch, eof, fail, opt, peek, terminated};
+use winnow::ascii::{Caseless, digit0, digit1, line_ending, space0, till_line_ending};
+use winnow::combinator::{
+    alt, delimited, dispatch, eof, fail, not, opt, peek, preceded, repeat, terminated,
+};
 use winnow::error::ContextError;
 use winnow::error::ParserError;
 use winnow::prelude::*;
-use winnow::token::{any, one_of, rest, take, take_until, take_while};
+use winnow::token::{any, none_of, one_of, rest, take, take_till, take_until, take_while};
 
 use crate::{Dialect, FormatOptions};
 
 pub(crate) fn tokenize<'a>(
+    input: &'a str,
+    named_placeholders: bool,
+    options: &FormatOptions,
+) -> Vec<Token<'a>> {
+    tokenize_script(input, named_placeholders, options, false)
+}
+
+/// Splits the body of a PostgreSQL function or `DO` block, whose PL/pgSQL control flow is
+/// laid out in blocks like PL/SQL's. Outside of bodies, `BEGIN` and `DECLARE` are statements.
+pub(crate) fn tokenize_function_body<'a>(
+    input: &'a str,
+    options: &FormatOptions,
+) -> Vec<Token<'a>> {
+    tokenize_script(input, false, options, true)
+}
+
+fn tokenize_script<'a>(
     mut input: &'a str,
     named_placeholders: bool,
     options: &FormatOptions,
+    procedural: bool,
 ) -> Vec<Token<'a>> {
     let mut tokens: Vec<Token> = Vec::new();
 
@@ -25,17 +45,55 @@ pub(crate) fn tokenize<'a>(
         tokens.push(result);
     }
 
+    // The statement delimiter, which MySQL scripts can change with `DELIMITER`
+    let mut delimiter = ";";
+    let mut blocks = ProceduralBlocks::default();
+    // The brackets open while tokenizing a DuckDB query, innermost last
+    let mut brackets = Vec::new();
+
     // Keep processing the string until it is empty
-    while let Ok(mut result) = get_next_token(
-        &mut input,
-        last_non_whitespace_token.clone(),
-        last_reserved_token.clone(),
-        last_reserved_top_level_token.clone(),
-        named_placeholders,
-        options.dialect,
-    ) {
+    while let Ok(mut result) = get_delimiter_token(&mut input, delimiter, &tokens, options.dialect)
+        .or_else(|_| {
+            get_next_token(
+                &mut input,
+                last_non_whitespace_token.clone(),
+                last_reserved_token.clone(),
+                last_reserved_top_level_token.clone(),
+                named_placeholders,
+                options.dialect,
+            )
+        })
+    {
+        if result.alias == ";" && is_delimiter_command(result.value) {
+            delimiter = result.value[DELIMITER_COMMAND.len()..].trim();
+        }
+        if procedural
+            || matches!(
+                options.dialect,
+                Dialect::Oracle | Dialect::SQLServer | Dialect::BigQuery | Dialect::MySql
+            )
+        {
+            blocks.classify(
+                &mut result,
+                last_non_whitespace_token.as_ref(),
+                options.dialect,
+            );
+        }
+
+        // DuckDB's list slices, e.g. `l[1:2]`, are written without spaces
+        if options.dialect == Dialect::DuckDb {
+            match result.kind {
+                TokenKind::OpenParen => brackets.push(result.value),
+                TokenKind::CloseParen => {
+                    brackets.pop();
+                }
+                _ if result.value == ":" && brackets.last() == Some(&"[") => result.alias = ".",
+                _ => {}
+            }
+        }
+
         match result.kind {
-            TokenKind::Reserved => {
+            TokenKind::Reserved | TokenKind::ReservedStatement => {
                 last_reserved_token = Some(result.clone());
             }
             TokenKind::ReservedTopLevel => {
@@ -93,6 +151,11 @@ pub(crate) enum TokenKind {
     Placeholder,
     Word,
     Join,
+    BlockStart,
+    BlockMiddle,
+    BlockEnd,
+    /// A T-SQL keyword starting a statement, which doesn't have to follow a `;`
+    ReservedStatement,
 }
 
 #[derive(Debug, Clone)]
@@ -129,9 +192,12 @@ fn get_next_token<'a>(
 ) -> Result<Token<'a>> {
     alt((
         alt((
-            get_comment_token,
-            |input: &mut _| get_type_specifier_token(input, previous_token.clone()),
+            |input: &mut _| get_comment_token(input, dialect),
+            |input: &mut _| get_type_specifier_token(input, previous_token.clone(), dialect),
             |input: &mut _| get_string_token(input, dialect),
+            |input: &mut _| get_tsql_token(input, dialect),
+            |input: &mut _| get_snowflake_token(input, previous_token.as_ref(), dialect),
+            |input: &mut _| get_function_keyword_token(input, dialect),
             |input: &mut _| get_open_paren_token(input, dialect),
             |input: &mut _| get_close_paren_token(input, dialect),
             get_number_token,
@@ -143,9 +209,11 @@ fn get_next_token<'a>(
                     previous_token.clone(),
                     last_reserved_token.clone(),
                     last_reserved_top_level_token.clone(),
+                    dialect,
                 )
             },
-            get_operator_token,
+            |input: &mut _| get_system_variable_token(input, dialect),
+            |input: &mut _| get_operator_token(input, dialect),
             |input: &mut _| get_placeholder_token(input, named_placeholders, dialect),
             get_word_token,
             get_any_other_char,
@@ -153,10 +221,398 @@ fn get_next_token<'a>(
     ))
     .parse_next(input)
 }
+const DELIMITER_COMMAND: &str = "DELIMITER";
+
+fn is_delimiter_command(token: &str) -> bool {
+    token
+        .get(..DELIMITER_COMMAND.len())
+        .is_some_and(|command| command.eq_ignore_ascii_case(DELIMITER_COMMAND))
+}
+
+/// MySQL clients let scripts change the statement delimiter, usually to `//` so that the
+/// procedure bodies that follow can contain `;`. Both the `DELIMITER` command, which has to
+/// start a line, and the custom delimiter are parsed as query separators.
+///
+/// The rows following PostgreSQL's `COPY ... FROM STDIN` aren't SQL, so they are kept as they
+/// are in the separator ending the statement, up to the `\.` line closing them.
+fn get_delimiter_token<'i>(
+    input: &mut &'i str,
+    delimiter: &str,
+    previous_tokens: &[Token<'i>],
+    dialect: Dialect,
+) -> Result<Token<'i>> {
+    if !matches!(
+        dialect,
+        Dialect::MySql | Dialect::Oracle | Dialect::PostgreSql
+    ) {
+        return fail.parse_next(input);
+    }
+    let separator = |token| Token {
+        kind: TokenKind::Operator,
+        value: token,
+        key: None,
+        alias: ";",
+    };
+
+    if dialect == Dialect::PostgreSql {
+        if copies_from_stdin(previous_tokens) {
+            return (
+                ';',
+                alt(((take_until(0.., "\n\\."), "\n\\.").void(), rest.void())),
+            )
+                .take()
+                .map(separator)
+                .parse_next(input);
+        }
+        return fail.parse_next(input);
+    }
+
+    let at_line_start = previous_tokens
+        .last()
+        .is_none_or(|token| token.kind == TokenKind::Whitespace && token.value.contains('\n'));
+    if dialect == Dialect::Oracle {
+        // SQL*Plus runs the current block when a line only contains a `/`
+        if at_line_start {
+            let mut attempt = *input;
+            let slash: Result<&str> =
+                terminated("/", peek((space0, alt((line_ending, eof))))).parse_next(&mut attempt);
+            if let Ok(slash) = slash {
+                *input = attempt;
+                return Ok(separator(slash));
+            }
+        }
+        return fail.parse_next(input);
+    }
+    if at_line_start {
+        let mut attempt = *input;
+        let command: Result<&str> = (
+            Caseless(DELIMITER_COMMAND),
+            take_while(1.., [' ', '\t']),
+            take_till(1.., char::is_whitespace),
+        )
+            .take()
+            .parse_next(&mut attempt);
+        if let Ok(command) = command {
+            *input = attempt;
+            return Ok(separator(command));
+        }
+    }
+    if delimiter != ";" && input.starts_with(delimiter) {
+        return Ok(separator(input.next_slice(delimiter.len())));
+    }
+    fail.parse_next(input)
+}
+
+/// Whether the statement being tokenized is a `COPY ... FROM STDIN`
+fn copies_from_stdin(previous_tokens: &[Token<'_>]) -> bool {
+    let mut statement = previous_tokens
+        .iter()
+        .rev()
+        .take_while(|token| token.alias != ";")
+        .filter(|token| token.kind != TokenKind::Whitespace)
+        .collect::<Vec<_>>();
+    statement.reverse();
+    statement
+        .first()
+        .is_some_and(|token| token.value.eq_ignore_ascii_case("COPY"))
+        && statement.windows(2).any(|pair| {
+            pair[0].value.eq_ignore_ascii_case("FROM")
+                && pair[1].value.eq_ignore_ascii_case("STDIN")
+        })
+}
+
+/// The kinds of objects T-SQL statements create, alter or drop, which an `IF EXISTS` may follow
+const TSQL_OBJECT_KINDS: &[&str] = &[
+    "COLUMN",
+    "CONSTRAINT",
+    "DATABASE",
+    "FUNCTION",
+    "INDEX",
+    "PROC",
+    "PROCEDURE",
+    "ROLE",
+    "SCHEMA",
+    "SEQUENCE",
+    "SYNONYM",
+    "TABLE",
+    "TRIGGER",
+    "TYPE",
+    "USER",
+    "VIEW",
+];
+
+/// The procedural blocks open while tokenizing an Oracle, T-SQL or BigQuery script, a MySQL
+/// stored program or a PL/pgSQL body, innermost last
+#[derive(Debug, Default)]
+struct ProceduralBlocks {
+    open: Vec<Block>,
+    /// A `PROCEDURE`, `FUNCTION` or `PACKAGE` header whose `IS` or `AS` starts a block
+    header: bool,
+    /// An `IF`, `ELSIF` or exception handler condition whose `THEN` starts a block, or a
+    /// `WHILE` or `FOR` condition whose `DO` does
+    condition: bool,
+    /// A MySQL stored program being defined, the only place `BEGIN` starts a block rather than
+    /// a transaction
+    routine: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    /// Parentheses and `CASE` expressions, whose keywords don't start blocks
+    Paren,
+    /// The declarations following `DECLARE` or the `IS` of a header
+    Declarations,
+    Begin,
+    Exception,
+    Then,
+    Else,
+    Loop,
+}
+
+impl ProceduralBlocks {
+    /// Turns the keywords starting, separating and ending blocks into the matching token kinds
+    fn classify(
+        &mut self,
+        token: &mut Token<'_>,
+        previous_token: Option<&Token<'_>>,
+        dialect: Dialect,
+    ) {
+        if token.alias == ";" {
+            self.header = false;
+            self.condition = false;
+            // The `/` terminator and MySQL's custom delimiters end the whole block
+            if token.value != ";" {
+                self.open.clear();
+            }
+            if self.open.is_empty() {
+                self.routine = false;
+            }
+            return;
+        }
+        match token.kind {
+            TokenKind::OpenParen => {
+                self.open.push(Block::Paren);
+                return;
+            }
+            TokenKind::CloseParen if self.open.last() == Some(&Block::Paren) => {
+                self.open.pop();
+                return;
+            }
+            TokenKind::Reserved
+            | TokenKind::ReservedTopLevel
+            | TokenKind::ReservedTopLevelNoIndent
+            | TokenKind::ReservedNewline
+            | TokenKind::CloseParen
+            | TokenKind::Word => {}
+            _ => return,
+        }
+
+        let word = token
+            .value
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_ascii_uppercase();
+        let top = self.open.last().copied();
+        let in_block = top.is_some_and(|block| block != Block::Paren);
+        // BigQuery scripts don't need a block around their control flow, which is told apart
+        // from functions like `IF(...)` and `REPEAT(...)` by starting a statement
+        let script_statement = dialect == Dialect::BigQuery
+            && previous_token.is_none_or(|token| {
+                token.alias == ";"
+                    || matches!(
+                        token.kind,
+                        TokenKind::BlockStart | TokenKind::BlockMiddle | TokenKind::BlockEnd
+                    )
+            });
+        if top == Some(Block::Paren) {
+            return;
+        }
+        if dialect == Dialect::SQLServer {
+            self.classify_tsql(token, previous_token, &word);
+            return;
+        }
+
+        token.kind = match (word.as_str(), top) {
+            ("DECLARE", _) if matches!(dialect, Dialect::Oracle | Dialect::PostgreSql) => {
+                self.open.push(Block::Declarations);
+                TokenKind::BlockStart
+            }
+            // BigQuery declares each variable in a statement of its own
+            ("DECLARE", _) => TokenKind::Reserved,
+            ("IS" | "AS", _) if self.header => {
+                self.header = false;
+                self.open.push(Block::Declarations);
+                TokenKind::BlockStart
+            }
+            ("BEGIN", Some(Block::Declarations)) => {
+                self.replace(Block::Begin);
+                TokenKind::BlockMiddle
+            }
+            ("BEGIN", _) if dialect != Dialect::MySql || self.routine || in_block => {
+                self.open.push(Block::Begin);
+                TokenKind::BlockStart
+            }
+            // Unlike PL/pgSQL's `RAISE EXCEPTION 'message'`
+            ("EXCEPTION", Some(Block::Begin)) if !ends_with_word(previous_token, &["RAISE"]) => {
+                self.replace(Block::Exception);
+                TokenKind::BlockMiddle
+            }
+            ("IF", _) if in_block || script_statement => {
+                self.condition = true;
+                return;
+            }
+            ("WHILE" | "FOR", _) if script_statement => {
+                self.condition = true;
+                return;
+            }
+            ("WHILE", _) if in_block && dialect == Dialect::MySql => {
+                self.condition = true;
+                return;
+            }
+            ("THEN", _) if self.condition => {
+                self.condition = false;
+                self.open.push(Block::Then);
+                TokenKind::BlockStart
+            }
+            ("DO", _)
+                if self.condition && matches!(dialect, Dialect::BigQuery | Dialect::MySql) =>
+            {
+                self.condition = false;
+                self.open.push(Block::Loop);
+                TokenKind::BlockStart
+            }
+            ("ELSIF" | "ELSEIF", Some(Block::Then)) => {
+                self.open.pop();
+                self.condition = true;
+                TokenKind::BlockEnd
+            }
+            ("ELSE", Some(Block::Then)) => {
+                self.replace(Block::Else);
+                TokenKind::BlockMiddle
+            }
+            // `EXIT WHEN done;`
+            ("WHEN", _) if ends_with_word(previous_token, &["EXIT", "CONTINUE"]) => {
+                TokenKind::Reserved
+            }
+            ("WHEN", Some(Block::Exception)) => {
+                self.condition = true;
+                return;
+            }
+            // The next exception handler
+            ("WHEN", Some(Block::Then))
+                if self.open.iter().nth_back(1) == Some(&Block::Exception) =>
+            {
+                self.open.pop();
+                self.condition = true;
+                TokenKind::BlockEnd
+            }
+            ("LOOP", _) if in_block || script_statement => {
+                self.open.push(Block::Loop);
+                TokenKind::BlockStart
+            }
+            ("REPEAT", _) if script_statement || in_block && dialect == Dialect::MySql => {
+                self.open.push(Block::Loop);
+                TokenKind::BlockStart
+            }
+            ("END IF" | "END LOOP" | "END WHILE" | "END FOR" | "END REPEAT", _) if in_block => {
+                self.open.pop();
+                TokenKind::BlockEnd
+            }
+            // An `END` also ends the exception handlers of its block
+            ("END", _) if in_block => {
+                while self.open.last() == Some(&Block::Then) {
+                    self.open.pop();
+                }
+                self.open.pop();
+                TokenKind::BlockEnd
+            }
+            // Subprograms declared in a package or a declaration section
+            ("PROCEDURE" | "FUNCTION", Some(Block::Declarations)) => {
+                self.header = true;
+                return;
+            }
+            _ if dialect == Dialect::Oracle
+                && token.kind == TokenKind::ReservedTopLevel
+                && word.starts_with("CREATE") =>
+            {
+                self.header = ["PROCEDURE", "FUNCTION", "PACKAGE", "PACKAGE BODY"]
+                    .iter()
+                    .any(|keyword| word.ends_with(keyword));
+                return;
+            }
+            _ if dialect == Dialect::MySql
+                && token.kind == TokenKind::ReservedTopLevel
+                && word.starts_with("CREATE") =>
+            {
+                self.routine = ["PROCEDURE", "FUNCTION", "TRIGGER"]
+                    .iter()
+                    .any(|keyword| word.ends_with(keyword));
+                return;
+            }
+            _ => return,
+        };
+    }
+
+    /// T-SQL only has `BEGIN ... END` blocks, which may be `TRY` or `CATCH` blocks. Its
+    /// statements don't need a `;`, so the keywords starting them start a new line.
+    fn classify_tsql(
+        &mut self,
+        token: &mut Token<'_>,
+        previous_token: Option<&Token<'_>>,
+        word: &str,
+    ) {
+        token.kind = match word {
+            "BEGIN" | "BEGIN TRY" | "BEGIN CATCH" => {
+                self.open.push(Block::Begin);
+                TokenKind::BlockStart
+            }
+            "END" | "END TRY" | "END CATCH" if !self.open.is_empty() => {
+                self.open.pop();
+                TokenKind::BlockEnd
+            }
+            // An `IF` only starts a statement where one can start, unlike the `IF EXISTS` and
+            // `IF NOT EXISTS` guards following the object kind of a `CREATE`, `ALTER` or `DROP`,
+            // e.g. `ALTER TABLE t DROP COLUMN IF EXISTS c`
+            "IF" if ends_with_word(previous_token, TSQL_OBJECT_KINDS) => return,
+            "IF" | "ELSE" | "WHILE" | "BREAK" | "CONTINUE" | "GOTO" | "RETURN" | "DECLARE"
+            | "PRINT" | "RAISERROR" | "THROW" | "WAITFOR" | "EXEC" | "EXECUTE" => {
+                TokenKind::ReservedStatement
+            }
+            _ if ["BEGIN ", "COMMIT", "ROLLBACK", "SAVE "]
+                .iter()
+                .any(|statement| word.starts_with(statement)) =>
+            {
+                TokenKind::ReservedStatement
+            }
+            _ => return,
+        };
+    }
+
+    fn replace(&mut self, block: Block) {
+        self.open.pop();
+        self.open.push(block);
+    }
+}
+
 fn get_type_specifier_token<'i>(
     input: &mut &'i str,
     previous_token: Option<Token<'i>>,
+    dialect: Dialect,
 ) -> Result<Token<'i>> {
+    // BigQuery's parameterized types, e.g. `ARRAY<STRUCT<a INT64>>`
+    if dialect == Dialect::BigQuery
+        && ends_with_word(previous_token.as_ref(), &["ARRAY", "STRUCT", "RANGE"])
+    {
+        if let Ok(token) = nested('<', '>').parse_next(input) {
+            return Ok(Token {
+                kind: TokenKind::TypeSpecifier,
+                value: token,
+                key: None,
+                alias: token,
+            });
+        }
+    }
     if previous_token.is_some_and(|token| {
         ![
             TokenKind::CloseParen,
@@ -179,6 +635,30 @@ fn get_type_specifier_token<'i>(
         })
     }
 }
+/// The text between an opening and a closing character, which may nest, such as the `<...>`
+/// parameters of a type
+fn nested<'i>(open: char, close: char) -> impl Parser<&'i str, &'i str, ContextError> {
+    move |input: &mut &'i str| {
+        if !input.starts_with(open) {
+            return fail.parse_next(input);
+        }
+        let mut depth = 0;
+        for (position, item) in input.char_indices() {
+            if item == open {
+                depth += 1;
+            } else if item == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(input.next_slice(position + 1));
+                }
+            } else if item == ';' {
+                break;
+            }
+        }
+        fail.parse_next(input)
+    }
+}
+
 fn get_whitespace_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
     take_while(1.., char::is_whitespace)
         .parse_next(input)
@@ -190,9 +670,17 @@ fn get_whitespace_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
         })
 }
 
-fn get_comment_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
+fn get_comment_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
     dispatch! {any;
-        '#' => till_line_ending.value(TokenKind::LineComment),
+        // `#` is an operator in PostgreSQL and starts temporary table names in T-SQL
+        '#' if !matches!(dialect, Dialect::PostgreSql | Dialect::SQLServer) => till_line_ending.value(TokenKind::LineComment),
+        // MySQL requires whitespace after `--`, so that `1--1` subtracts a negative number
+        '-' if dialect == Dialect::MySql => (
+            '-',
+            alt((eof.void(), peek(one_of(char::is_whitespace)).void())),
+            till_line_ending,
+        )
+            .value(TokenKind::LineComment),
         '-' => ('-', till_line_ending).value(TokenKind::LineComment),
         '/' => ('*', alt((take_until(0.., "*/"), rest)), opt(take(2usize))).value(TokenKind::BlockComment),
         _ => fail,
@@ -237,6 +725,25 @@ pub fn take_till_escaping<'a>(
     }
 }
 
+/// Like [`take_till_escaping`], but a backslash escapes any character, as in MySQL strings
+fn take_till_backslash_escaping<'a>(desired: char) -> impl Parser<&'a str, &'a str, ContextError> {
+    move |input: &mut &'a str| {
+        let mut chars = input.char_indices().peekable();
+        while let Some((byte_pos, item)) = chars.next() {
+            if item == '\\' {
+                chars.next();
+            } else if item == desired {
+                if chars.peek().is_some_and(|&(_, next)| next == desired) {
+                    chars.next();
+                } else {
+                    return Ok(input.next_slice(byte_pos));
+                }
+            }
+        }
+        rest.parse_next(input)
+    }
+}
+
 // This enables the following string patterns:
 // 1. backtick quoted string using `` to escape
 // 2. square bracket quoted string (SQL Server) using ]] to escape
@@ -244,10 +751,23 @@ pub fn take_till_escaping<'a>(
 // 4. single quoted string using '' or \' to escape
 // 5. national character quoted string using N'' or N\' to escape
 // 6. hex(blob literal) does not need to escape
+// 7. in MySQL, single and double quoted strings where a backslash escapes any character
+// 8. in Oracle, strings where a backslash is a plain character and `q'[...]'` quoted strings
 fn get_string_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
-    dispatch! {any;
+    alt((
+        |input: &mut _| get_bigquery_string(input, dialect),
+        dispatch! {any;
+        '"' if dialect == Dialect::Oracle => (take_till_escaping('"', &['"']), any).void(),
+        '\'' if dialect == Dialect::Oracle => (take_till_escaping('\'', &['\'']), any).void(),
+        'q' | 'Q' if dialect == Dialect::Oracle => alternative_quoting,
+        // Snowflake's `$$`-delimited procedure bodies
+        '$' if dialect == Dialect::Snowflake => ('$', take_until(0.., "$$"), "$$").void(),
+        // PostgreSQL's dollar-quoted strings, `$$...$$` or `$tag$...$tag$`
+        '$' if dialect == Dialect::PostgreSql => dollar_quoting,
         '`' => (take_till_escaping('`', &['`']), any).void(),
-        '[' if dialect == Dialect::SQLServer => (take_till_escaping(']', &[']']), any).void(),
+        '[' if has_bracketed_identifiers(dialect) => (take_till_escaping(']', &[']']), any).void(),
+        '"' if dialect == Dialect::MySql => (take_till_backslash_escaping('"'), any).void(),
+        '\'' if dialect == Dialect::MySql => (take_till_backslash_escaping('\''), any).void(),
         '"' => (take_till_escaping('"', &['"', '\\']), any).void(),
         '\'' => (take_till_escaping('\'', &['\'', '\\']), any).void(),
         'N' => ('\'', take_till_escaping('\'', &['\'', '\\']), any).void(),
@@ -255,8 +775,9 @@ fn get_string_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'
         'x' => ('\'', take_till_escaping('\'', &[]), any).void(),
         'X' => ('\'', take_till_escaping('\'', &[]), any).void(),
         _ => fail,
-    }
-    .take()
+        }
+        .take(),
+    ))
     .parse_next(input)
     .map(|token| Token {
         kind: TokenKind::String,
@@ -266,11 +787,71 @@ fn get_string_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'
     })
 }
 
+/// BigQuery's strings can be raw (`r'\d'`), bytes (`b'...'`) or both, and any of them can be
+/// triple-quoted to span lines
+fn get_bigquery_string<'i>(input: &mut &'i str, dialect: Dialect) -> Result<&'i str> {
+    if dialect != Dialect::BigQuery {
+        return fail.parse_next(input);
+    }
+    let prefix = alt((Caseless("rb"), Caseless("br"), Caseless("r"), Caseless("b")));
+    (
+        opt(prefix),
+        alt((
+            ("'''", take_until(0.., "'''"), "'''").void(),
+            ("\"\"\"", take_until(0.., "\"\"\""), "\"\"\"").void(),
+            ('\'', take_till_backslash_escaping('\''), '\'').void(),
+            ('"', take_till_backslash_escaping('"'), '"').void(),
+        )),
+    )
+        .take()
+        .parse_next(input)
+}
+
+/// The rest of a PostgreSQL dollar-quoted string, which ends with the same tag it starts with
+fn dollar_quoting(input: &mut &str) -> Result<()> {
+    let tag = terminated(
+        opt((
+            one_of(|c: char| c.is_alphabetic() || c == '_'),
+            take_while(0.., is_word_character),
+        ))
+        .take(),
+        '$',
+    )
+    .parse_next(input)?;
+    let end = format!("${tag}$");
+    (take_until(0.., end.as_str()), take(end.len()))
+        .void()
+        .parse_next(input)
+}
+
+/// The body of Oracle's `q'<delimiter>...<delimiter>'` strings, where the closing delimiter of
+/// `[`, `{`, `(` and `<` is the matching bracket
+fn alternative_quoting(input: &mut &str) -> Result<()> {
+    let open = preceded('\'', any).parse_next(input)?;
+    let close = match open {
+        '[' => ']',
+        '{' => '}',
+        '(' => ')',
+        '<' => '>',
+        c if c.is_whitespace() => return fail.parse_next(input),
+        c => c,
+    };
+    let end = format!("{close}'");
+    (take_until(0.., end.as_str()), take(end.len()))
+        .void()
+        .parse_next(input)
+}
+
+/// SQLite accepts SQL Server's `[bracketed identifiers]` for compatibility
+fn has_bracketed_identifiers(dialect: Dialect) -> bool {
+    matches!(dialect, Dialect::SQLServer | Dialect::Sqlite)
+}
+
 // Like above but it doesn't replace double quotes
 fn get_placeholder_string_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
     dispatch! {any;
         '`'=>( take_till_escaping('`', &['`']), any).void(),
-        '[' if dialect == Dialect::SQLServer =>( take_till_escaping(']', &[']']), any).void(),
+        '[' if has_bracketed_identifiers(dialect) =>( take_till_escaping(']', &[']']), any).void(),
         '"'=>( take_till_escaping('"', &['\\']), any).void(),
         '\''=>( take_till_escaping('\'', &['\\']), any).void(),
         'N' =>('\'', take_till_escaping('\'', &['\\']), any).void(),
@@ -286,12 +867,133 @@ fn get_placeholder_string_token<'i>(input: &mut &'i str, dialect: Dialect) -> Re
     })
 }
 
+/// T-SQL's `#temp` and `##global` table names, and its keywords starting with `BEGIN`, `END`
+/// or `WITH`, which have to be recognized before the blocks these words start
+fn get_tsql_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
+    if dialect != Dialect::SQLServer {
+        return fail.parse_next(input);
+    }
+    let token = |kind, value| Token {
+        kind,
+        value,
+        key: None,
+        alias: value,
+    };
+
+    let mut attempt = *input;
+    let temp_table: Result<&str> = (take_while(1..=2, '#'), take_while(1.., is_word_character))
+        .take()
+        .parse_next(&mut attempt);
+    if let Ok(name) = temp_table {
+        *input = attempt;
+        return Ok(token(TokenKind::Word, name));
+    }
+
+    let uc_input = get_uc_words(input, 3);
+    let mut uc_input = uc_input.as_str();
+    let transaction = alt(("TRANSACTION", "TRAN"));
+    let result: Result<(TokenKind, &str)> = alt((
+        terminated(alt(("BEGIN TRY", "BEGIN CATCH")), end_of_word)
+            .map(|word| (TokenKind::ReservedTopLevelNoIndent, word)),
+        terminated(alt(("END TRY", "END CATCH")), end_of_word)
+            .map(|word| (TokenKind::CloseParen, word)),
+        terminated(
+            (
+                alt((
+                    "BEGIN DISTRIBUTED ",
+                    "BEGIN ",
+                    "COMMIT ",
+                    "ROLLBACK ",
+                    "SAVE ",
+                )),
+                transaction,
+            )
+                .take(),
+            end_of_word,
+        )
+        .map(|word| (TokenKind::Reserved, word)),
+        terminated("WITH TIES", end_of_word).map(|word| (TokenKind::Reserved, word)),
+        // Table hints, e.g. `FROM t WITH (NOLOCK)`, rather than a common table expression
+        terminated("WITH", (opt(" "), peek("("))).map(|word| (TokenKind::Reserved, word)),
+    ))
+    .parse_next(&mut uc_input);
+    if let Ok((kind, word)) = result {
+        Ok(token(kind, finalize(input, word)))
+    } else {
+        Err(ParserError::from_input(input))
+    }
+}
+
+/// Snowflake's `@stage/paths/`, the `:` of semi-structured paths such as `col:field.sub`,
+/// formatted like a `.`, and the row pattern of `MATCH_RECOGNIZE`, which is kept as written
+fn get_snowflake_token<'i>(
+    input: &mut &'i str,
+    previous_token: Option<&Token<'i>>,
+    dialect: Dialect,
+) -> Result<Token<'i>> {
+    if dialect != Dialect::Snowflake {
+        return fail.parse_next(input);
+    }
+    let path_follows = previous_token.is_some_and(|token| {
+        matches!(
+            token.kind,
+            TokenKind::Word | TokenKind::String | TokenKind::CloseParen | TokenKind::Placeholder
+        )
+    });
+
+    alt((
+        (
+            '@',
+            take_while(0.., |item: char| {
+                !item.is_whitespace() && !matches!(item, ';' | ',' | '(' | ')' | '\'')
+            }),
+        )
+            .take()
+            .map(|value| (TokenKind::Word, value, value)),
+        terminated(
+            ":",
+            peek(one_of(|item| is_word_character(item) || item == '"')),
+        )
+        .verify(|_: &str| path_follows)
+        .map(|value| (TokenKind::Operator, value, ".")),
+        nested('(', ')')
+            .verify(|_: &str| ends_with_word(previous_token, &["PATTERN"]))
+            .map(|value| (TokenKind::Word, value, value)),
+    ))
+    .parse_next(input)
+    .map(|(kind, value, alias)| Token {
+        kind,
+        value,
+        key: None,
+        alias,
+    })
+}
+
+/// Keywords formatted like the function calls they look like, DuckDB's `COLUMNS(...)` expression,
+/// Trino's `ROW(...)` constructor and the `FIRST(...)` of Snowflake's `MATCH_RECOGNIZE`
+fn get_function_keyword_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
+    let keyword = match dialect {
+        Dialect::DuckDb => "COLUMNS",
+        Dialect::Snowflake => "FIRST",
+        Dialect::Trino => "ROW",
+        _ => return fail.parse_next(input),
+    };
+    terminated(Caseless(keyword), peek('('))
+        .parse_next(input)
+        .map(|token| Token {
+            kind: TokenKind::Word,
+            value: token,
+            key: None,
+            alias: token,
+        })
+}
+
 fn get_open_paren_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
     let case = terminated(Caseless("CASE"), end_of_word);
-    let open_paren = if dialect == Dialect::PostgreSql {
-        ("(", "[", case)
+    let open_paren = if has_array_brackets(dialect) {
+        ("(", "[", struct_brace("{", dialect), case)
     } else {
-        ("(", "(", case)
+        ("(", "(", struct_brace("{", dialect), case)
     };
 
     alt(open_paren).parse_next(input).map(|token| Token {
@@ -311,10 +1013,42 @@ fn get_close_paren_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<To
     )
         .take();
     let end = terminated(Caseless("END"), end_of_word);
-    let close_paren = if dialect == Dialect::PostgreSql {
-        (")", "]", end_case, end)
+    // PL/SQL's and PL/pgSQL's `END IF` and `END LOOP`, and the `END WHILE`, `END FOR` and
+    // `END REPEAT` of BigQuery scripts
+    let end_statement = |input: &mut &'i str| {
+        let statements = match dialect {
+            Dialect::Oracle | Dialect::PostgreSql => &["IF", "LOOP"][..],
+            Dialect::BigQuery => &["IF", "LOOP", "WHILE", "FOR", "REPEAT"][..],
+            _ => return fail.parse_next(input),
+        };
+        (
+            Caseless("END"),
+            take_while(1.., char::is_whitespace),
+            take_while(1.., is_word_character)
+                .verify(|word: &str| statements.iter().any(|s| word.eq_ignore_ascii_case(s))),
+            end_of_word,
+        )
+            .take()
+            .parse_next(input)
+    };
+    let close_paren = if has_array_brackets(dialect) {
+        (
+            ")",
+            "]",
+            struct_brace("}", dialect),
+            end_case,
+            end_statement,
+            end,
+        )
     } else {
-        (")", ")", end_case, end)
+        (
+            ")",
+            ")",
+            struct_brace("}", dialect),
+            end_case,
+            end_statement,
+            end,
+        )
     };
     alt(close_paren).parse_next(input).map(|token| Token {
         kind: TokenKind::CloseParen,
@@ -324,6 +1058,42 @@ fn get_close_paren_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<To
     })
 }
 
+/// Array literals and subscripts, e.g. `ARRAY[1, 2][1]`
+fn has_array_brackets(dialect: Dialect) -> bool {
+    matches!(
+        dialect,
+        Dialect::PostgreSql
+            | Dialect::BigQuery
+            | Dialect::Snowflake
+            | Dialect::DuckDb
+            | Dialect::Trino
+            | Dialect::Redshift
+    )
+}
+
+/// The braces of DuckDB's struct literals, e.g. `{'a': 1}`, which other dialects use for
+/// placeholders
+fn struct_brace<'i>(
+    mut brace: &'static str,
+    dialect: Dialect,
+) -> impl Parser<&'i str, &'i str, ContextError> {
+    move |input: &mut &'i str| {
+        if dialect != Dialect::DuckDb {
+            return fail.parse_next(input);
+        }
+        brace.parse_next(input)
+    }
+}
+
+/// The characters starting named placeholders, DuckDB only using `$name`
+fn named_placeholder_prefixes(dialect: Dialect) -> &'static [char] {
+    if dialect == Dialect::DuckDb {
+        &['$']
+    } else {
+        &['@', ':', '$']
+    }
+}
+
 fn get_placeholder_token<'i>(
     input: &mut &'i str,
     named_placeholders: bool,
@@ -334,38 +1104,38 @@ fn get_placeholder_token<'i>(
 
     if named_placeholders {
         alt((
-            get_ident_named_placeholder_token,
+            |input: &mut _| get_ident_named_placeholder_token(input, dialect),
             |input: &mut _| get_string_named_placeholder_token(input, dialect),
-            get_indexed_placeholder_token,
-            get_braced_named_placeholder_token,
+            |input: &mut _| get_indexed_placeholder_token(input, dialect),
+            |input: &mut _| get_braced_named_placeholder_token(input, dialect),
         ))
         .parse_next(input)
     } else {
         alt((
-            get_indexed_placeholder_token,
-            get_ident_named_placeholder_token,
+            |input: &mut _| get_indexed_placeholder_token(input, dialect),
+            |input: &mut _| get_ident_named_placeholder_token(input, dialect),
             |input: &mut _| get_string_named_placeholder_token(input, dialect),
-            get_braced_named_placeholder_token,
+            |input: &mut _| get_braced_named_placeholder_token(input, dialect),
         ))
         .parse_next(input)
     }
 }
 
-fn get_indexed_placeholder_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
+fn get_indexed_placeholder_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
     alt(((one_of(('?', '$')), digit1).take(), "?"))
         .parse_next(input)
         .map(|token| Token {
             kind: TokenKind::Placeholder,
             value: token,
             key: if token.len() > 1 {
-                if let Ok(index) = token[1..].parse::<usize>() {
-                    Some(if token.starts_with('$') {
-                        PlaceholderKind::OneIndexed(index)
-                    } else {
-                        PlaceholderKind::ZeroIndexed(index)
-                    })
-                } else {
-                    None
+                match token[1..].parse::<usize>() {
+                    // SQLite numbers its `?NNN` parameters from 1
+                    Ok(0) if dialect == Dialect::Sqlite => None,
+                    Ok(index) if token.starts_with('$') || dialect == Dialect::Sqlite => {
+                        Some(PlaceholderKind::OneIndexed(index))
+                    }
+                    Ok(index) => Some(PlaceholderKind::ZeroIndexed(index)),
+                    Err(_) => None,
                 }
             } else {
                 None
@@ -374,31 +1144,60 @@ fn get_indexed_placeholder_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
         })
 }
 
-fn get_ident_named_placeholder_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
-    (
-        one_of(('@', ':', '$')),
+fn get_ident_named_placeholder_token<'i>(
+    input: &mut &'i str,
+    dialect: Dialect,
+) -> Result<Token<'i>> {
+    let ident = || {
         take_while(1.., |item: char| {
             item.is_alphanumeric() || item == '.' || item == '_' || item == '$'
-        }),
-    )
-        .take()
-        .parse_next(input)
-        .map(|token| {
-            let index = Cow::Borrowed(&token[1..]);
-            Token {
-                kind: TokenKind::Placeholder,
-                value: token,
-                key: Some(PlaceholderKind::Named(index)),
-                alias: token,
-            }
         })
+    };
+    // SQLite also accepts TCL style `$name::part(suffix)` names
+    let tcl_name = |input: &mut &'i str| {
+        if dialect != Dialect::Sqlite {
+            return fail.parse_next(input);
+        }
+        (
+            '$',
+            ident(),
+            repeat::<_, _, (), _, _>(0.., ("::", ident())),
+            opt(delimited('(', take_till(0.., ')'), ')')),
+        )
+            .take()
+            .parse_next(input)
+    };
+    alt((
+        tcl_name,
+        (one_of(named_placeholder_prefixes(dialect)), ident()).take(),
+    ))
+    .parse_next(input)
+    .map(|token| {
+        let index = Cow::Borrowed(&token[1..]);
+        Token {
+            kind: TokenKind::Placeholder,
+            value: token,
+            key: Some(PlaceholderKind::Named(index)),
+            alias: token,
+        }
+    })
 }
 
-fn get_braced_named_placeholder_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
-    delimited('{', take_until(1.., '}'), '}')
+fn get_braced_named_placeholder_token<'i>(
+    input: &mut &'i str,
+    dialect: Dialect,
+) -> Result<Token<'i>> {
+    // Including the `${var}` substitution variables of Spark and Hive
+    let substitution = '$'.verify(|_: &char| dialect == Dialect::Spark);
+    preceded(opt(substitution), delimited('{', take_until(1.., '}'), '}'))
         .with_taken()
         .parse_next(input)
         .map(|(index, token)| {
+            // ClickHouse's `{name:Type}` parameters declare their type
+            let index = match index.split_once(':') {
+                Some((name, _)) if dialect == Dialect::ClickHouse => name.trim(),
+                _ => index,
+            };
             let index = Cow::Borrowed(index);
             Token {
                 kind: TokenKind::Placeholder,
@@ -413,9 +1212,10 @@ fn get_string_named_placeholder_token<'i>(
     input: &mut &'i str,
     dialect: Dialect,
 ) -> Result<Token<'i>> {
-    (one_of(('@', ':')), |input: &mut _| {
-        get_placeholder_string_token(input, dialect)
-    })
+    (
+        one_of(('@', ':')).verify(|_: &char| dialect != Dialect::DuckDb),
+        |input: &mut _| get_placeholder_string_token(input, dialect),
+    )
         .take()
         .parse_next(input)
         .map(|token| {
@@ -466,10 +1266,11 @@ fn get_reserved_word_token<'a>(
     previous_token: Option<Token<'a>>,
     last_reserved_token: Option<Token<'a>>,
     last_reserved_top_level_token: Option<Token<'a>>,
+    dialect: Dialect,
 ) -> Result<Token<'a>> {
     // A reserved word cannot be preceded by a "."
     // this makes it so in "my_table.from", "from" is not considered a reserved word
-    if let Some(token) = previous_token {
+    if let Some(token) = &previous_token {
         if token.value == "." {
             return Err(ParserError::from_input(input));
         }
@@ -479,17 +1280,518 @@ fn get_reserved_word_token<'a>(
         return Err(ParserError::from_input(input));
     }
 
+    if dialect == Dialect::Sqlite {
+        return alt((
+            get_top_level_reserved_token(
+                previous_token.clone(),
+                last_reserved_top_level_token,
+                dialect,
+            ),
+            get_newline_after_reserved_token(dialect),
+            get_newline_reserved_token(last_reserved_token.clone()),
+            get_join_token(previous_token.clone(), dialect),
+            |input: &mut _| get_top_level_reserved_token_no_indent(input, dialect),
+            |input: &mut _| get_plain_reserved_two_token(input, dialect),
+            get_sqlite_keyword_token,
+        ))
+        .verify(|token: &Token<'a>| {
+            is_sqlite_reserved(token, previous_token.as_ref(), last_reserved_token.as_ref())
+        })
+        .parse_next(input);
+    }
+
     alt((
-        get_top_level_reserved_token(last_reserved_top_level_token),
-        get_newline_after_reserved_token(),
+        alt((
+            |input: &mut _| get_option_token(input, dialect),
+            |input: &mut _| {
+                get_table_clause_token(input, last_reserved_top_level_token.as_ref(), dialect)
+            },
+            |input: &mut _| {
+                get_redshift_option_token(input, last_reserved_top_level_token.as_ref(), dialect)
+            },
+        )),
+        get_top_level_reserved_token(
+            previous_token.clone(),
+            last_reserved_top_level_token.clone(),
+            dialect,
+        ),
+        get_newline_after_reserved_token(dialect),
         get_newline_reserved_token(last_reserved_token),
-        get_join_token(),
-        get_top_level_reserved_token_no_indent,
-        get_plain_reserved_token,
+        get_join_token(previous_token, dialect),
+        |input: &mut _| get_top_level_reserved_token_no_indent(input, dialect),
+        |input: &mut _| get_plain_reserved_token(input, dialect),
+        |input: &mut _| get_dialect_keyword_token(input, dialect),
     ))
     .parse_next(input)
 }
 
+/// The options of Snowflake's `COPY INTO` and `CREATE STAGE`
+const SNOWFLAKE_OPTIONS: &[&str] = &[
+    "COPY_OPTIONS",
+    "CREDENTIALS",
+    "DIRECTORY",
+    "ENCRYPTION",
+    "FILES",
+    "FILE_FORMAT",
+    "FORCE",
+    "HEADER",
+    "MATCH_BY_COLUMN_NAME",
+    "MAX_FILE_SIZE",
+    "ON_ERROR",
+    "OVERWRITE",
+    "PATTERN",
+    "PURGE",
+    "SINGLE",
+    "STORAGE_INTEGRATION",
+    "URL",
+    "VALIDATION_MODE",
+];
+
+/// An option of Snowflake's `COPY INTO` or `CREATE STAGE`, or the `ENGINE` of a ClickHouse
+/// table, which starts a line when it is set
+fn get_option_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
+    let options = match dialect {
+        Dialect::Snowflake => SNOWFLAKE_OPTIONS,
+        Dialect::ClickHouse => &["ENGINE"],
+        _ => return fail.parse_next(input),
+    };
+    terminated(
+        take_while(1.., is_word_character).verify(|word: &str| {
+            options
+                .binary_search(&word.to_ascii_uppercase().as_str())
+                .is_ok()
+        }),
+        peek((space0, '=', not('>'))),
+    )
+    .parse_next(input)
+    .map(|token| Token {
+        kind: TokenKind::ReservedNewline,
+        value: token,
+        key: None,
+        alias: token,
+    })
+}
+
+/// A storage clause of a Spark or Hive `CREATE TABLE`, a distribution or sort key of a
+/// Redshift one, or a clause of a PostgreSQL `CREATE POLICY`, which starts a line
+fn get_table_clause_token<'i>(
+    input: &mut &'i str,
+    last_reserved_top_level_token: Option<&Token<'_>>,
+    dialect: Dialect,
+) -> Result<Token<'i>> {
+    let in_create = last_reserved_top_level_token.is_some_and(|token| {
+        token
+            .value
+            .split_whitespace()
+            .next()
+            .is_some_and(|word| word.eq_ignore_ascii_case("CREATE"))
+    });
+    if !in_create {
+        return fail.parse_next(input);
+    }
+    let uc_input = get_uc_words(input, 3);
+    let mut uc_input = uc_input.as_str();
+
+    let mut spark_clauses = alt((
+        terminated("USING", end_of_word),
+        terminated("PARTITIONED BY", end_of_word),
+        terminated("CLUSTERED BY", end_of_word),
+        terminated("SORTED BY", end_of_word),
+        terminated("STORED AS", end_of_word),
+        terminated("ROW FORMAT", end_of_word),
+        terminated("LOCATION", end_of_word),
+        terminated("TBLPROPERTIES", end_of_word),
+        terminated("OPTIONS", end_of_word),
+    ));
+    // The keys of columns don't list columns
+    let mut redshift_clauses = alt((
+        terminated("DISTSTYLE", end_of_word),
+        terminated(
+            alt((
+                "DISTKEY",
+                "SORTKEY",
+                "COMPOUND SORTKEY",
+                "INTERLEAVED SORTKEY",
+            )),
+            peek((space0, '(')),
+        ),
+    ));
+    // The command of a policy is a single clause, so `FOR SELECT` doesn't start a query
+    let mut policy_clauses = alt((
+        terminated(
+            ("AS ", alt(("PERMISSIVE", "RESTRICTIVE"))).take(),
+            end_of_word,
+        ),
+        terminated(
+            ("FOR ", alt(("ALL", "SELECT", "INSERT", "UPDATE", "DELETE"))).take(),
+            end_of_word,
+        ),
+        terminated("TO", end_of_word),
+        terminated("USING", end_of_word),
+        terminated("WITH CHECK", end_of_word),
+    ));
+    let result: Result<&str> = match dialect {
+        Dialect::Spark => spark_clauses.parse_next(&mut uc_input),
+        Dialect::Redshift => redshift_clauses.parse_next(&mut uc_input),
+        Dialect::PostgreSql if ends_with_word(last_reserved_top_level_token, &["POLICY"]) => {
+            policy_clauses.parse_next(&mut uc_input)
+        }
+        _ => return fail.parse_next(input),
+    };
+
+    result.map(|token| {
+        let token = finalize(input, token);
+        Token {
+            kind: TokenKind::ReservedNewline,
+            value: token,
+            key: None,
+            alias: token,
+        }
+    })
+}
+
+/// The options of Redshift's `COPY` and `UNLOAD` taking a value
+const REDSHIFT_OPTIONS: &[&str] = &[
+    "CREDENTIALS",
+    "DATEFORMAT",
+    "DELIMITER",
+    "FORMAT",
+    "IAM_ROLE",
+    "IGNOREHEADER",
+    "MAXFILESIZE",
+    "PARALLEL",
+    "REGION",
+    "TIMEFORMAT",
+];
+
+/// An option of Redshift's `COPY ... FROM` or `UNLOAD ... TO`, which starts a line
+fn get_redshift_option_token<'i>(
+    input: &mut &'i str,
+    last_reserved_top_level_token: Option<&Token<'_>>,
+    dialect: Dialect,
+) -> Result<Token<'i>> {
+    if dialect != Dialect::Redshift
+        || !ends_with_word(last_reserved_top_level_token, &["FROM", "TO"])
+    {
+        return fail.parse_next(input);
+    }
+    terminated(
+        take_while(1.., is_word_character).verify(|word: &str| {
+            REDSHIFT_OPTIONS
+                .binary_search(&word.to_ascii_uppercase().as_str())
+                .is_ok()
+        }),
+        // Unlike a table alias
+        peek((space0, none_of([',', ';', ')', '\n', '\r']))),
+    )
+    .parse_next(input)
+    .map(|token| Token {
+        kind: TokenKind::ReservedNewline,
+        value: token,
+        key: None,
+        alias: token,
+    })
+}
+
+/// The T-SQL control-of-flow statements, table hints and clause keywords missing from the
+/// reserved words of the other dialects
+const TSQL_KEYWORDS: &[&str] = &[
+    "BREAK",
+    "CONTINUE",
+    "GOTO",
+    "HOLDLOCK",
+    "NOCOUNT",
+    "NOLOCK",
+    "NOWAIT",
+    "OUTPUT",
+    "PAGLOCK",
+    "PERCENT",
+    "PRINT",
+    "RAISERROR",
+    "READPAST",
+    "READUNCOMMITTED",
+    "ROWLOCK",
+    "TABLOCK",
+    "TABLOCKX",
+    "THROW",
+    "TOP",
+    "TRAN",
+    "TRANSACTION",
+    "UPDLOCK",
+    "WAITFOR",
+    "WHILE",
+    "XLOCK",
+];
+
+/// The BigQuery scripting statements missing from the reserved words of the other
+/// dialects
+const BIGQUERY_KEYWORDS: &[&str] = &[
+    "BREAK",
+    "CONTINUE",
+    "ERROR",
+    "ITERATE",
+    "LEAVE",
+    "TRANSACTION",
+    "UNTIL",
+    "WHILE",
+];
+
+/// The ClickHouse modifiers of tables and columns missing from the reserved words of the other
+/// dialects
+const CLICKHOUSE_KEYWORDS: &[&str] = &["FINAL", "SAMPLE", "TTL"];
+
+/// The DuckDB statements and modifiers missing from the reserved words of the other dialects
+const DUCKDB_KEYWORDS: &[&str] = &["EXCLUDE", "INSTALL", "PIVOT", "POSITIONAL", "UNPIVOT"];
+
+/// The Trino table modifiers missing from the reserved words of the other dialects
+const TRINO_KEYWORDS: &[&str] = &["BERNOULLI", "ORDINALITY", "SYSTEM_TIME", "TABLESAMPLE"];
+
+/// The Redshift table attributes and `COPY` and `UNLOAD` options missing from the reserved words
+/// of the other dialects
+const REDSHIFT_KEYWORDS: &[&str] = &[
+    "COMPOUND",
+    "CREDENTIALS",
+    "DISTKEY",
+    "DISTSTYLE",
+    "ENCODE",
+    "IAM_ROLE",
+    "IGNOREHEADER",
+    "INTERLEAVED",
+    "SORTKEY",
+    "UNLOAD",
+];
+
+/// The PL/pgSQL statements and `COPY` streams missing from the reserved words of the other
+/// dialects
+const POSTGRESQL_KEYWORDS: &[&str] = &["FOREACH", "PERFORM", "RAISE", "STDIN", "STDOUT"];
+
+fn get_dialect_keyword_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
+    let keywords = match dialect {
+        Dialect::SQLServer => TSQL_KEYWORDS,
+        Dialect::BigQuery => BIGQUERY_KEYWORDS,
+        Dialect::ClickHouse => CLICKHOUSE_KEYWORDS,
+        Dialect::DuckDb => DUCKDB_KEYWORDS,
+        Dialect::Trino => TRINO_KEYWORDS,
+        Dialect::Redshift => REDSHIFT_KEYWORDS,
+        Dialect::PostgreSql => POSTGRESQL_KEYWORDS,
+        _ => return fail.parse_next(input),
+    };
+    take_while(1.., is_word_character)
+        .verify(|word: &str| {
+            keywords
+                .binary_search(&word.to_ascii_uppercase().as_str())
+                .is_ok()
+        })
+        .parse_next(input)
+        .map(|token| Token {
+            kind: TokenKind::Reserved,
+            value: token,
+            key: None,
+            alias: token,
+        })
+}
+
+/// The keywords of SQLite, as listed in its documentation, plus the `TRUE` and `FALSE`
+/// literals and the `STRICT` table option
+const SQLITE_KEYWORDS: &[&str] = &[
+    "ABORT",
+    "ACTION",
+    "ADD",
+    "AFTER",
+    "ALL",
+    "ALTER",
+    "ALWAYS",
+    "ANALYZE",
+    "AND",
+    "AS",
+    "ASC",
+    "ATTACH",
+    "AUTOINCREMENT",
+    "BEFORE",
+    "BEGIN",
+    "BETWEEN",
+    "BY",
+    "CASCADE",
+    "CASE",
+    "CAST",
+    "CHECK",
+    "COLLATE",
+    "COLUMN",
+    "COMMIT",
+    "CONFLICT",
+    "CONSTRAINT",
+    "CREATE",
+    "CROSS",
+    "CURRENT",
+    "CURRENT_DATE",
+    "CURRENT_TIME",
+    "CURRENT_TIMESTAMP",
+    "DATABASE",
+    "DEFAULT",
+    "DEFERRABLE",
+    "DEFERRED",
+    "DELETE",
+    "DESC",
+    "DETACH",
+    "DISTINCT",
+    "DO",
+    "DROP",
+    "EACH",
+    "ELSE",
+    "END",
+    "ESCAPE",
+    "EXCEPT",
+    "EXCLUDE",
+    "EXCLUSIVE",
+    "EXISTS",
+    "EXPLAIN",
+    "FAIL",
+    "FALSE",
+    "FILTER",
+    "FIRST",
+    "FOLLOWING",
+    "FOR",
+    "FOREIGN",
+    "FROM",
+    "FULL",
+    "GENERATED",
+    "GLOB",
+    "GROUP",
+    "GROUPS",
+    "HAVING",
+    "IF",
+    "IGNORE",
+    "IMMEDIATE",
+    "IN",
+    "INDEX",
+    "INDEXED",
+    "INITIALLY",
+    "INNER",
+    "INSERT",
+    "INSTEAD",
+    "INTERSECT",
+    "INTO",
+    "IS",
+    "ISNULL",
+    "JOIN",
+    "KEY",
+    "LAST",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "MATCH",
+    "MATERIALIZED",
+    "NATURAL",
+    "NO",
+    "NOT",
+    "NOTHING",
+    "NOTNULL",
+    "NULL",
+    "NULLS",
+    "OF",
+    "OFFSET",
+    "ON",
+    "OR",
+    "ORDER",
+    "OTHERS",
+    "OUTER",
+    "OVER",
+    "PARTITION",
+    "PLAN",
+    "PRAGMA",
+    "PRECEDING",
+    "PRIMARY",
+    "QUERY",
+    "RAISE",
+    "RANGE",
+    "RECURSIVE",
+    "REFERENCES",
+    "REGEXP",
+    "REINDEX",
+    "RELEASE",
+    "RENAME",
+    "REPLACE",
+    "RESTRICT",
+    "RETURNING",
+    "RIGHT",
+    "ROLLBACK",
+    "ROW",
+    "ROWS",
+    "SAVEPOINT",
+    "SELECT",
+    "SET",
+    "STRICT",
+    "TABLE",
+    "TEMP",
+    "TEMPORARY",
+    "THEN",
+    "TIES",
+    "TO",
+    "TRANSACTION",
+    "TRIGGER",
+    "TRUE",
+    "UNBOUNDED",
+    "UNION",
+    "UNIQUE",
+    "UPDATE",
+    "USING",
+    "VACUUM",
+    "VALUES",
+    "VIEW",
+    "VIRTUAL",
+    "WHEN",
+    "WHERE",
+    "WINDOW",
+    "WITH",
+    "WITHOUT",
+];
+
+fn get_sqlite_keyword_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
+    take_while(1.., is_word_character)
+        .verify(|word: &str| {
+            SQLITE_KEYWORDS
+                .binary_search(&word.to_ascii_uppercase().as_str())
+                .is_ok()
+        })
+        .parse_next(input)
+        .map(|token| Token {
+            kind: TokenKind::Reserved,
+            value: token,
+            key: None,
+            alias: token,
+        })
+}
+
+/// SQLite lets most of its keywords name columns and tables, so a reserved word has to start
+/// with one of its keywords, and the ones commonly used as names are only reserved where
+/// they can't be one
+fn is_sqlite_reserved(
+    token: &Token<'_>,
+    previous_token: Option<&Token<'_>>,
+    last_reserved_token: Option<&Token<'_>>,
+) -> bool {
+    let follows = |words: &[&str]| ends_with_word(previous_token, words);
+    let mut words = token.value.split_whitespace();
+    let first_word = words.next().unwrap_or_default().to_ascii_uppercase();
+    let single_word = words.next().is_none();
+
+    match first_word.as_str() {
+        "KEY" if single_word => follows(&["PRIMARY", "FOREIGN"]),
+        "REPLACE" if single_word => follows(&["OR", "CONFLICT"]),
+        // `WITHOUT ROWID, STRICT` or `(...) STRICT`
+        "STRICT" => {
+            follows(&[")"]) || (follows(&[","]) && ends_with_word(last_reserved_token, &["ROWID"]))
+        }
+        word => SQLITE_KEYWORDS.binary_search(&word).is_ok(),
+    }
+}
+
+fn ends_with_word(token: Option<&Token<'_>>, words: &[&str]) -> bool {
+    token
+        .and_then(|token| token.value.split_whitespace().next_back())
+        .is_some_and(|last| words.iter().any(|word| last.eq_ignore_ascii_case(word)))
+}
+
 // We have to be a bit creative here for performance reasons
 fn get_uc_words(input: &str, words: usize) -> String {
     input
@@ -507,60 +1809,129 @@ fn finalize<'a>(input: &mut &'a str, token: &str) -> &'a str {
 }
 
 fn get_top_level_reserved_token<'a>(
+    previous_token: Option<Token<'a>>,
     last_reserved_top_level_token: Option<Token<'a>>,
+    dialect: Dialect,
 ) -> impl Parser<&'a str, Token<'a>, ContextError> {
     move |input: &mut &'a str| {
-        let uc_input: String = get_uc_words(input, 4);
+        let uc_input: String = get_uc_words(input, 6);
         let mut uc_input = uc_input.as_str();
 
         // First peek at the first character to determine which group to check
         let first_char = peek(any).parse_next(input)?.to_ascii_uppercase();
 
-        let creatable = (
-            "AGGREGATE",
-            "FUNCTION",
-            "LANGUAGE",
-            "PROCEDURE",
-            "RULE",
-            "TRIGGER",
-            "VIEW",
-        );
+        let creatable = || {
+            alt((
+                alt((
+                    "AGGREGATE",
+                    "FUNCTION",
+                    "LANGUAGE",
+                    "PROCEDURE",
+                    "RULE",
+                    "TRIGGER",
+                    "VIEW",
+                )),
+                "STAGE".verify(|_: &str| dialect == Dialect::Snowflake),
+                alt(("POLICY", "TYPE")).verify(|_: &str| dialect == Dialect::PostgreSql),
+                alt(("PACKAGE BODY", "PACKAGE")).verify(|_: &str| dialect == Dialect::Oracle),
+            ))
+        };
 
-        let alterable_or_droppable = alt((alt(creatable), "TABLE", "INDEX"));
+        let alterable_or_droppable = alt((creatable(), "TABLE", "INDEX"));
+        // SQLite's `INSERT OR REPLACE` and `ON CONFLICT REPLACE`
+        let conflict_resolution = || {
+            alt(("ROLLBACK", "ABORT", "REPLACE", "FAIL", "IGNORE"))
+                .verify(|_: &str| dialect == Dialect::Sqlite)
+        };
+        let clickhouse_clause = |input: &mut _| {
+            get_clickhouse_clause(
+                input,
+                dialect,
+                previous_token.as_ref(),
+                last_reserved_top_level_token.as_ref(),
+            )
+        };
+        let pivot_clause = |input: &mut _| {
+            get_pivot_clause(
+                input,
+                dialect,
+                previous_token.as_ref(),
+                last_reserved_top_level_token.as_ref(),
+            )
+        };
+        let spark_clause = |input: &mut _| get_spark_clause(input, dialect);
+        let trino_clause = |input: &mut _| get_trino_clause(input, dialect);
+        // The `CREATE OR REPLACE TABLE` of Spark and the warehouses
+        let or_replace_table = "OR REPLACE ".verify(|_: &str| {
+            matches!(
+                dialect,
+                Dialect::Spark | Dialect::Snowflake | Dialect::BigQuery | Dialect::DuckDb
+            )
+        });
+        let redshift_clause = |input: &mut _| {
+            get_redshift_clause(input, dialect, last_reserved_top_level_token.as_ref())
+        };
+        // The options of a Redshift `UNLOAD`, which include a `PARTITION BY`
+        let in_unload = dialect == Dialect::Redshift
+            && ends_with_word(last_reserved_top_level_token.as_ref(), &["TO"]);
+        // The `USING` of a DuckDB `PIVOT` lists its aggregates
+        let pivot_using = dialect == Dialect::DuckDb
+            && ends_with_word(last_reserved_top_level_token.as_ref(), &["PIVOT", "ON"]);
+        // The clauses a T-SQL `MERGE` can be in when it reaches its next `WHEN MATCHED` branch
+        let in_merge = dialect == Dialect::SQLServer
+            && last_reserved_top_level_token.as_ref().is_some_and(|token| {
+                ["USING", "UPDATE", "INSERT", "VALUES"]
+                    .iter()
+                    .any(|clause| token.alias.eq_ignore_ascii_case(clause))
+            });
         // Match keywords based on their first letter
         let result: Result<&str> = match first_char {
             'A' => alt((
+                |input: &mut _| get_match_recognize_clause(input, dialect),
                 terminated("AFTER", end_of_word),
                 terminated(("ALTER ", alterable_or_droppable).take(), end_of_word),
             ))
             .parse_next(&mut uc_input),
 
-            'C' => terminated(
-                (
-                    "CREATE ",
-                    alt((
-                        (opt("OR REPLACE "), alt(creatable)).take(),
-                        (opt("UNIQUE "), "INDEX").take(),
-                        (
-                            opt(alt((
-                                "UNLOGGED ",
-                                (
-                                    alt(("GLOBAL ", "LOCAL ")),
-                                    opt(alt(("TEMPORARY ", "TEMP "))),
-                                )
-                                    .take(),
-                            ))),
-                            "TABLE",
-                        )
-                            .take(),
-                    )),
-                )
-                    .take(),
-                end_of_word,
-            )
+            'C' => alt((
+                |input: &mut _| get_hierarchical_clause(input, dialect),
+                terminated("COPY INTO", end_of_word)
+                    .verify(|_: &str| dialect == Dialect::Snowflake),
+                spark_clause,
+                redshift_clause,
+                terminated(
+                    (
+                        "CREATE ",
+                        alt((
+                            (opt("OR REPLACE "), creatable()).take(),
+                            (opt("UNIQUE "), "INDEX").take(),
+                            (
+                                opt(or_replace_table),
+                                opt(alt((
+                                    "UNLOGGED ",
+                                    alt(("TEMPORARY ", "TEMP ", "VIRTUAL "))
+                                        .verify(|_: &str| dialect == Dialect::Sqlite),
+                                    "EXTERNAL ".verify(|_: &str| dialect == Dialect::Spark),
+                                    (
+                                        alt(("GLOBAL ", "LOCAL ")),
+                                        opt(alt(("TEMPORARY ", "TEMP "))),
+                                    )
+                                        .take(),
+                                ))),
+                                "TABLE",
+                            )
+                                .take(),
+                        )),
+                    )
+                        .take(),
+                    end_of_word,
+                ),
+            ))
             .parse_next(&mut uc_input),
 
             'D' => alt((
+                |input: &mut _| get_match_recognize_clause(input, dialect),
+                spark_clause,
                 terminated("DELETE FROM", end_of_word),
                 terminated(
                     ("DROP ", alterable_or_droppable, opt(" IF EXISTS")).take(),
@@ -569,9 +1940,16 @@ fn get_top_level_reserved_token<'a>(
             ))
             .parse_next(&mut uc_input),
 
-            'E' => terminated("EXCEPT", end_of_word).parse_next(&mut uc_input),
+            'E' => alt((
+                terminated(("EXCEPT", alt((" DISTINCT", " ALL"))).take(), end_of_word)
+                    .verify(|_: &str| dialect == Dialect::BigQuery),
+                terminated("EXCEPT", end_of_word),
+            ))
+            .parse_next(&mut uc_input),
 
             'F' => alt((
+                clickhouse_clause,
+                trino_clause,
                 terminated("FETCH FIRST", end_of_word),
                 terminated("FROM", end_of_word),
                 terminated(
@@ -594,7 +1972,21 @@ fn get_top_level_reserved_token<'a>(
             'H' => terminated("HAVING", end_of_word).parse_next(&mut uc_input),
 
             'I' => alt((
+                pivot_clause,
+                // Hive's `INSERT OVERWRITE TABLE t PARTITION (...)` and `INSERT INTO TABLE t`
+                terminated(
+                    alt((
+                        ("INSERT OVERWRITE", opt(alt((" TABLE", " INTO")))).take(),
+                        "INSERT INTO TABLE",
+                    )),
+                    end_of_word,
+                )
+                .verify(|_: &str| dialect == Dialect::Spark),
                 terminated("INSERT INTO", end_of_word),
+                terminated(
+                    ("INSERT OR ", conflict_resolution(), " INTO").take(),
+                    end_of_word,
+                ),
                 terminated("INSERT", end_of_word),
             ))
             .parse_next(&mut uc_input),
@@ -602,42 +1994,100 @@ fn get_top_level_reserved_token<'a>(
             'L' => terminated("LIMIT", end_of_word).parse_next(&mut uc_input),
 
             'M' => alt((
+                |input: &mut _| get_match_recognize_clause(input, dialect),
                 terminated("MODIFY", end_of_word),
                 terminated("MERGE INTO", end_of_word),
+                terminated("MERGE", end_of_word).verify(|_: &str| dialect == Dialect::SQLServer),
             ))
             .parse_next(&mut uc_input),
 
             'O' => alt((
+                pivot_clause,
+                |input: &mut _| {
+                    get_tsql_clause(input, dialect, last_reserved_top_level_token.as_ref())
+                },
+                |input: &mut _| get_match_recognize_clause(input, dialect),
                 terminated("ORDER BY", end_of_word),
-                terminated("ON CONFLICT", end_of_word),
+                // SQLite's `UNIQUE ON CONFLICT REPLACE` constraints aren't upserts
+                terminated(
+                    "ON CONFLICT",
+                    (end_of_word, not((" ", conflict_resolution(), end_of_word))),
+                ),
             ))
             .parse_next(&mut uc_input),
 
-            'P' => terminated("PARTITION BY", end_of_word).parse_next(&mut uc_input),
+            'P' => alt((
+                |input: &mut _| get_match_recognize_clause(input, dialect),
+                clickhouse_clause,
+                pivot_clause,
+                terminated("PARTITION BY", end_of_word),
+            ))
+            .parse_next(&mut uc_input),
 
-            'R' => terminated("RETURNING", end_of_word).parse_next(&mut uc_input),
+            'Q' => terminated("QUALIFY", end_of_word)
+                .verify(|_: &str| {
+                    matches!(
+                        dialect,
+                        Dialect::BigQuery | Dialect::Snowflake | Dialect::DuckDb
+                    )
+                })
+                .parse_next(&mut uc_input),
+
+            'R' => alt((
+                terminated("RETURNING", end_of_word),
+                terminated("REPLACE INTO", end_of_word)
+                    .verify(|_: &str| dialect == Dialect::Sqlite),
+            ))
+            .parse_next(&mut uc_input),
 
             'S' => alt((
+                alt((
+                    |input: &mut _| {
+                        get_tsql_clause(input, dialect, last_reserved_top_level_token.as_ref())
+                    },
+                    clickhouse_clause,
+                    spark_clause,
+                )),
                 terminated("SELECT DISTINCT", end_of_word),
                 terminated("SELECT ALL", end_of_word),
                 terminated("SELECT", end_of_word),
                 terminated("SET CURRENT SCHEMA", end_of_word),
                 terminated("SET SCHEMA", end_of_word),
                 terminated("SET", end_of_word),
+                |input: &mut _| get_hierarchical_clause(input, dialect),
             ))
             .parse_next(&mut uc_input),
 
             'U' => alt((
+                pivot_clause,
+                redshift_clause,
+                terminated(("UPDATE OR ", conflict_resolution()).take(), end_of_word),
                 terminated("UPDATE", end_of_word),
                 terminated("USING", end_of_word),
             ))
             .parse_next(&mut uc_input),
 
+            'T' => {
+                alt((clickhouse_clause, redshift_clause, trino_clause)).parse_next(&mut uc_input)
+            }
+
             'V' => terminated("VALUES", end_of_word).parse_next(&mut uc_input),
 
             'W' => alt((
                 terminated("WHERE", end_of_word),
                 terminated("WINDOW", end_of_word),
+                terminated(
+                    (
+                        "WHEN ",
+                        opt("NOT "),
+                        "MATCHED",
+                        opt(alt((" BY TARGET", " BY SOURCE"))),
+                        opt(" THEN"),
+                    )
+                        .take(),
+                    end_of_word,
+                )
+                .verify(|_: &str| in_merge),
             ))
             .parse_next(&mut uc_input),
 
@@ -657,10 +2107,46 @@ fn get_top_level_reserved_token<'a>(
                 {
                     TokenKind::Reserved
                 }
+                // BigQuery's `SELECT * EXCEPT (a)`
+                ("EXCEPT", _)
+                    if dialect == Dialect::BigQuery
+                        && previous_token.as_ref().is_some_and(|t| t.value == "*") =>
+                {
+                    TokenKind::Reserved
+                }
                 ("SET", Some("UPDATE")) => TokenKind::ReservedNewlineAfter,
-                ("USING", v) if v != Some("MERGE INTO") && v != Some("DELETE FROM") => {
+                (word, _)
+                    if in_unload
+                        && word
+                            .get(..9)
+                            .is_some_and(|word| word.eq_ignore_ascii_case("PARTITION")) =>
+                {
+                    TokenKind::ReservedNewline
+                }
+                (word, _) if pivot_using && word.eq_ignore_ascii_case("USING") => {
+                    TokenKind::ReservedTopLevel
+                }
+                // MySQL's `ON DUPLICATE KEY UPDATE a = VALUES(a)`
+                ("VALUES", _)
+                    if dialect == Dialect::MySql
+                        && previous_token.as_ref().is_some_and(|t| t.value == "=") =>
+                {
                     TokenKind::Reserved
                 }
+                ("USING", v)
+                    if v != Some("MERGE INTO")
+                        && v != Some("MERGE")
+                        && v != Some("DELETE FROM") =>
+                {
+                    TokenKind::Reserved
+                }
+                // The `WHEN [NOT] MATCHED ... THEN` branches of a `MERGE`
+                _ if token
+                    .get(..4)
+                    .is_some_and(|word| word.eq_ignore_ascii_case("WHEN")) =>
+                {
+                    TokenKind::ReservedTopLevelNoIndent
+                }
                 _ => TokenKind::ReservedTopLevel,
             };
 
@@ -668,6 +2154,8 @@ fn get_top_level_reserved_token<'a>(
                 "CREATE"
             } else if token.starts_with("SELECT") {
                 "SELECT"
+            } else if token.starts_with("UPDATE") {
+                "UPDATE"
             } else {
                 token
             };
@@ -684,7 +2172,167 @@ fn get_top_level_reserved_token<'a>(
     }
 }
 
-fn get_join_token<'a>() -> impl Parser<&'a str, Token<'a>, ContextError> {
+/// T-SQL's `SELECT TOP (n)`, whose row count the formatter keeps on the line of the keyword,
+/// and the `OUTPUT` clause of data modification statements
+fn get_tsql_clause<'i>(
+    input: &mut &'i str,
+    dialect: Dialect,
+    last_reserved_top_level_token: Option<&Token<'_>>,
+) -> Result<&'i str> {
+    if dialect != Dialect::SQLServer {
+        return fail.parse_next(input);
+    }
+    // Unlike the `OUTPUT` of a procedure parameter, the clause follows the keywords of an
+    // `INSERT`, `UPDATE`, `DELETE` or `MERGE`, and precedes columns
+    let modifies_data = last_reserved_top_level_token
+        .and_then(|token| token.value.split_whitespace().next())
+        .is_some_and(|word| {
+            ["INSERT", "UPDATE", "DELETE", "MERGE", "VALUES"]
+                .iter()
+                .any(|statement| word.eq_ignore_ascii_case(statement))
+        });
+    alt((
+        terminated(
+            ("SELECT ", opt(alt(("DISTINCT ", "ALL "))), "TOP").take(),
+            end_of_word,
+        ),
+        terminated(
+            "OUTPUT",
+            (end_of_word, peek((" ", none_of([',', ')', ';'])))),
+        )
+        .verify(|_: &str| modifies_data),
+    ))
+    .parse_next(input)
+}
+
+/// Redshift's `COPY t FROM 's3://...'` and `UNLOAD ('...') TO 's3://...'` statements
+fn get_redshift_clause<'i>(
+    input: &mut &'i str,
+    dialect: Dialect,
+    last_reserved_top_level_token: Option<&Token<'_>>,
+) -> Result<&'i str> {
+    if dialect != Dialect::Redshift {
+        return fail.parse_next(input);
+    }
+    let in_unload = ends_with_word(last_reserved_top_level_token, &["UNLOAD"]);
+    alt((
+        terminated("COPY", end_of_word),
+        terminated("UNLOAD", end_of_word),
+        terminated("TO", end_of_word).verify(|_: &str| in_unload),
+    ))
+    .parse_next(input)
+}
+
+/// The clauses of Spark and Hive queries choosing how rows are distributed and sorted within
+/// partitions
+fn get_spark_clause<'i>(input: &mut &'i str, dialect: Dialect) -> Result<&'i str> {
+    if dialect != Dialect::Spark {
+        return fail.parse_next(input);
+    }
+    alt((
+        terminated("CLUSTER BY", end_of_word),
+        terminated("DISTRIBUTE BY", end_of_word),
+        terminated("SORT BY", end_of_word),
+    ))
+    .parse_next(input)
+}
+
+/// The clauses of Trino queries sampling a table or reading it as of a point in time
+fn get_trino_clause<'i>(input: &mut &'i str, dialect: Dialect) -> Result<&'i str> {
+    if dialect != Dialect::Trino {
+        return fail.parse_next(input);
+    }
+    alt((
+        terminated("TABLESAMPLE", end_of_word),
+        terminated("FOR SYSTEM_TIME AS OF", end_of_word),
+    ))
+    .parse_next(input)
+}
+
+/// DuckDB's `PIVOT t ON a USING sum(b)` and `UNPIVOT t ON a, b INTO NAME n VALUE v` statements,
+/// which can also be subqueries
+fn get_pivot_clause<'i>(
+    input: &mut &'i str,
+    dialect: Dialect,
+    previous_token: Option<&Token<'_>>,
+    last_reserved_top_level_token: Option<&Token<'_>>,
+) -> Result<&'i str> {
+    if dialect != Dialect::DuckDb {
+        return fail.parse_next(input);
+    }
+    let statement_start =
+        previous_token.is_none_or(|token| token.alias == ";" || token.kind == TokenKind::OpenParen);
+    let in_pivot = ends_with_word(last_reserved_top_level_token, &["PIVOT", "UNPIVOT"]);
+    let in_unpivot_columns = ends_with_word(last_reserved_top_level_token, &["ON"]);
+    alt((
+        terminated(alt(("PIVOT", "UNPIVOT")), end_of_word).verify(|_: &str| statement_start),
+        terminated("ON", end_of_word).verify(|_: &str| in_pivot),
+        terminated("INTO", end_of_word).verify(|_: &str| in_unpivot_columns),
+    ))
+    .parse_next(input)
+}
+
+/// The clauses of ClickHouse queries and of the engine of its tables
+fn get_clickhouse_clause<'i>(
+    input: &mut &'i str,
+    dialect: Dialect,
+    previous_token: Option<&Token<'_>>,
+    last_reserved_top_level_token: Option<&Token<'_>>,
+) -> Result<&'i str> {
+    if dialect != Dialect::ClickHouse {
+        return fail.parse_next(input);
+    }
+    // Columns can have a `TTL` too, and the column list a `PRIMARY KEY`
+    let in_engine = ends_with_word(last_reserved_top_level_token, &["BY", "KEY"]);
+    let in_column_list = ends_with_word(previous_token, &[",", "("]);
+    alt((
+        terminated("PREWHERE", end_of_word),
+        terminated("SAMPLE BY", end_of_word),
+        terminated("SETTINGS", end_of_word),
+        // Unlike the `format()` function
+        terminated("FORMAT", (end_of_word, not((space0, '(')))),
+        terminated("PRIMARY KEY", end_of_word).verify(|_: &str| !in_column_list),
+        terminated("TTL", end_of_word).verify(|_: &str| in_engine),
+    ))
+    .parse_next(input)
+}
+
+/// The clauses of Snowflake's `MATCH_RECOGNIZE`, except for its `PARTITION BY` and `ORDER BY`
+fn get_match_recognize_clause<'i>(input: &mut &'i str, dialect: Dialect) -> Result<&'i str> {
+    if dialect != Dialect::Snowflake {
+        return fail.parse_next(input);
+    }
+    alt((
+        terminated("MEASURES", end_of_word),
+        terminated(
+            alt(("ONE ROW PER MATCH", "ALL ROWS PER MATCH")),
+            end_of_word,
+        ),
+        terminated("AFTER MATCH SKIP", end_of_word),
+        // Unlike the `PATTERN = '...'` option of `COPY INTO`
+        terminated("PATTERN", (end_of_word, opt(" "), peek("("))),
+        terminated("DEFINE", end_of_word),
+    ))
+    .parse_next(input)
+}
+
+/// Oracle's `CONNECT BY` and `START WITH` clauses of hierarchical queries
+fn get_hierarchical_clause<'i>(input: &mut &'i str, dialect: Dialect) -> Result<&'i str> {
+    if dialect != Dialect::Oracle {
+        return fail.parse_next(input);
+    }
+    alt((
+        terminated("CONNECT BY NOCYCLE", end_of_word),
+        terminated("CONNECT BY", end_of_word),
+        terminated("START WITH", end_of_word),
+    ))
+    .parse_next(input)
+}
+
+fn get_join_token<'a>(
+    previous_token: Option<Token<'a>>,
+    dialect: Dialect,
+) -> impl Parser<&'a str, Token<'a>, ContextError> {
     move |input: &mut &'a str| {
         let uc_input: String = get_uc_words(input, 3);
         let mut uc_input = uc_input.as_str();
@@ -720,22 +2368,58 @@ fn get_join_token<'a>() -> impl Parser<&'a str, Token<'a>, ContextError> {
             terminated("ASOF JOIN", end_of_word),
             terminated("LEFT ASOF JOIN", end_of_word),
             terminated("PASTE JOIN", end_of_word),
+            terminated("STRAIGHT_JOIN", end_of_word).verify(|_: &str| dialect == Dialect::MySql),
             terminated("GLOBAL INNER JOIN", end_of_word),
             terminated("GLOBAL LEFT JOIN", end_of_word),
             terminated("GLOBAL RIGHT JOIN", end_of_word),
             terminated("GLOBAL FULL JOIN", end_of_word),
         ));
 
+        // ClickHouse's joins with the elements of an array
+        let array_joins = alt((
+            terminated("ARRAY JOIN", end_of_word),
+            terminated("LEFT ARRAY JOIN", end_of_word),
+            terminated("INNER ARRAY JOIN", end_of_word),
+        ))
+        .verify(|_: &str| dialect == Dialect::ClickHouse);
+
+        // DuckDB's joins matching the closest or the same position
+        let duckdb_joins = alt((
+            terminated("ASOF LEFT JOIN", end_of_word),
+            terminated("POSITIONAL JOIN", end_of_word),
+            terminated("ANTI JOIN", end_of_word),
+        ))
+        .verify(|_: &str| dialect == Dialect::DuckDb);
+
+        // Hive's lateral views joining the rows generated by a function
+        let lateral_views = alt((
+            terminated("LATERAL VIEW OUTER", end_of_word),
+            terminated("LATERAL VIEW", end_of_word),
+        ))
+        .verify(|_: &str| dialect == Dialect::Spark);
+
         // Combine all parsers
-        let result: Result<&str> =
-            alt((standard_joins, specific_joins, special_joins)).parse_next(&mut uc_input);
+        let result: Result<&str> = alt((
+            standard_joins,
+            specific_joins,
+            special_joins,
+            array_joins,
+            duckdb_joins,
+            lateral_views,
+        ))
+        .parse_next(&mut uc_input);
 
         if let Ok(token) = result {
             let final_word = token.split(' ').next_back().unwrap();
             let input_end_pos =
                 input.to_ascii_uppercase().find(final_word).unwrap() + final_word.len();
             let token = input.next_slice(input_end_pos);
-            let kind = TokenKind::Join;
+            // MySQL's `SELECT STRAIGHT_JOIN` is a modifier of the select rather than a join
+            let kind = if previous_token.as_ref().is_some_and(|t| t.alias == "SELECT") {
+                TokenKind::Reserved
+            } else {
+                TokenKind::Join
+            };
             Ok(Token {
                 kind,
                 value: token,
@@ -748,14 +2432,20 @@ fn get_join_token<'a>() -> impl Parser<&'a str, Token<'a>, ContextError> {
     }
 }
 
-fn get_newline_after_reserved_token<'a>() -> impl Parser<&'a str, Token<'a>, ContextError> {
+fn get_newline_after_reserved_token<'a>(
+    dialect: Dialect,
+) -> impl Parser<&'a str, Token<'a>, ContextError> {
     move |input: &mut &'a str| {
-        let uc_input: String = get_uc_words(input, 3);
+        let uc_input: String = get_uc_words(input, 4);
         let mut uc_input = uc_input.as_str();
 
-        let mut on_conflict = terminated("DO UPDATE SET", end_of_word);
+        let mut upsert = alt((
+            terminated("DO UPDATE SET", end_of_word),
+            terminated("ON DUPLICATE KEY UPDATE", end_of_word)
+                .verify(|_: &str| dialect == Dialect::MySql),
+        ));
 
-        let result: Result<&str> = on_conflict.parse_next(&mut uc_input);
+        let result: Result<&str> = upsert.parse_next(&mut uc_input);
 
         if let Ok(token) = result {
             let value = finalize(input, token);
@@ -827,19 +2517,48 @@ fn get_newline_reserved_token<'a>(
     }
 }
 
-fn get_top_level_reserved_token_no_indent<'i>(input: &mut &'i str) -> Result<Token<'i>> {
+fn get_top_level_reserved_token_no_indent<'i>(
+    input: &mut &'i str,
+    dialect: Dialect,
+) -> Result<Token<'i>> {
     let uc_input = get_uc_words(input, 2);
     let mut uc_input = uc_input.as_str();
 
     let result: Result<&str> = alt((
-        terminated("BEGIN", end_of_word),
-        terminated("DECLARE", end_of_word),
-        terminated("INTERSECT ALL", end_of_word),
-        terminated("INTERSECT", end_of_word),
-        terminated("MINUS", end_of_word),
-        terminated("UNION ALL", end_of_word),
-        terminated("UNION", end_of_word),
-        terminated("WITH", end_of_word),
+        // BigQuery's `BEGIN TRANSACTION` and `UNION DISTINCT`
+        terminated(
+            alt(("BEGIN TRANSACTION", "INTERSECT DISTINCT", "UNION DISTINCT")),
+            end_of_word,
+        )
+        .verify(|_: &str| dialect == Dialect::BigQuery),
+        alt((
+            terminated("BEGIN", end_of_word),
+            terminated("DECLARE", end_of_word),
+            terminated("INTERSECT ALL", end_of_word),
+            terminated("INTERSECT", end_of_word),
+            terminated("MINUS", end_of_word),
+            terminated("UNION ALL", end_of_word),
+            terminated("UNION", end_of_word),
+        )),
+        // Unlike Trino's `UNNEST(...) WITH ORDINALITY`, and PostgreSQL's `WITH (...)` options of
+        // a statement and `WITH &&` operators of an exclusion constraint
+        terminated(
+            "WITH",
+            (
+                end_of_word,
+                not(alt((
+                    " ORDINALITY"
+                        .verify(|_: &str| dialect == Dialect::Trino)
+                        .void(),
+                    (
+                        opt(' '),
+                        one_of(['(', '=', '<', '>', '&', '|', '-', '~', '@', '#']),
+                    )
+                        .verify(|_: &(_, char)| dialect == Dialect::PostgreSql)
+                        .void(),
+                ))),
+            ),
+        ),
         terminated("$$", end_of_word),
     ))
     .parse_next(&mut uc_input);
@@ -855,8 +2574,12 @@ fn get_top_level_reserved_token_no_indent<'i>(input: &mut &'i str) -> Result<Tok
         Err(ParserError::from_input(input))
     }
 }
-fn get_plain_reserved_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
-    alt((get_plain_reserved_two_token, get_plain_reserved_one_token)).parse_next(input)
+fn get_plain_reserved_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
+    alt((
+        |input: &mut _| get_plain_reserved_two_token(input, dialect),
+        get_plain_reserved_one_token,
+    ))
+    .parse_next(input)
 }
 fn get_plain_reserved_one_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
     let uc_input = get_uc_words(input, 1);
@@ -1315,18 +3038,38 @@ fn get_plain_reserved_one_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
     }
 }
 
-fn get_plain_reserved_two_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
-    let uc_input = get_uc_words(input, 2);
+fn get_plain_reserved_two_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
+    let uc_input = get_uc_words(input, 3);
     let mut uc_input = uc_input.as_str();
+
+    // MySQL index hints, e.g. `USE INDEX FOR ORDER BY (idx)`
+    let index_hints = alt((
+        terminated(
+            (alt(("USE ", "FORCE ", "IGNORE ")), alt(("INDEX", "KEY"))).take(),
+            end_of_word,
+        ),
+        terminated(
+            ("FOR ", alt(("JOIN", "ORDER BY", "GROUP BY"))).take(),
+            end_of_word,
+        ),
+    ))
+    .verify(|_: &str| dialect == Dialect::MySql);
+
     let result: Result<&str> = alt((
+        index_hints,
         terminated("CHARACTER SET", end_of_word),
         terminated("DO NOTHING", end_of_word),
-        terminated("ON CONFLICT", end_of_word),
-        terminated("ON CONSTRAINT", end_of_word),
-        terminated("ON DELETE", end_of_word),
-        terminated("ON UPDATE", end_of_word),
+        alt((
+            terminated("ON CONFLICT", end_of_word),
+            terminated("ON CONSTRAINT", end_of_word),
+            terminated("ON DELETE", end_of_word),
+            terminated("ON UPDATE", end_of_word),
+        )),
         terminated("DISTINCT FROM", end_of_word),
         terminated("PARTITIONED BY", end_of_word),
+        // The options of PostgreSQL's sequences and identity columns
+        terminated("START WITH", end_of_word).verify(|_: &str| dialect == Dialect::PostgreSql),
+        terminated("WITHOUT ROWID", end_of_word).verify(|_: &str| dialect == Dialect::Sqlite),
     ))
     .parse_next(&mut uc_input);
     if let Ok(token) = result {
@@ -1353,12 +3096,34 @@ fn get_word_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
         })
 }
 
-fn get_operator_token<'i>(input: &mut &'i str) -> Result<Token<'i>> {
+/// `@@` system variables, e.g. BigQuery's `@@error.message` or T-SQL's `@@ROWCOUNT`, which
+/// aren't placeholders
+fn get_system_variable_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
+    if !matches!(
+        dialect,
+        Dialect::SQLServer | Dialect::MySql | Dialect::BigQuery
+    ) {
+        return fail.parse_next(input);
+    }
+    ("@@", take_while(1.., is_word_character))
+        .take()
+        .parse_next(input)
+        .map(|token| Token {
+            kind: TokenKind::Word,
+            value: token,
+            key: None,
+            alias: token,
+        })
+}
+
+fn get_operator_token<'i>(input: &mut &'i str, dialect: Dialect) -> Result<Token<'i>> {
     const ALLOWED_OPERATORS: [char; 16] = [
         '!', '<', '>', '=', '|', ':', '-', '~', '*', '&', '@', '^', '?', '#', '/', '%',
     ];
 
     take_while(2..=5, ALLOWED_OPERATORS)
+        // A MySQL `--` that doesn't start a comment is two minus signs
+        .verify(|token: &str| dialect != Dialect::MySql || !token.contains("--"))
         .map(|token: &str| Token {
             kind: TokenKind::Operator,
             value: token,
@@ -1,6 +1,7 @@
 use std::fs;
+use std::io::Write;
 use std::path::PathBuf;
-use std::process::Command;
+use std::process::{Command, Output, Stdio};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 fn sqlformat_bin() -> PathBuf {
@@ -19,16 +20,53 @@ fn unique_temp_file_path(name: &str) -> PathBuf {
     ))
 }
 
-#[test]
-fn exits_non_zero_and_prints_usage_for_missing_filename() {
-    let output = Command::new(sqlformat_bin())
-        .output()
+fn run_with_stdin(args: &[&str], input: &[u8]) -> Output {
+    let mut child = Command::new(sqlformat_bin())
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
         .expect("binary should execute");
+    child
+        .stdin
+        .take()
+        .expect("stdin should be piped")
+        .write_all(input)
+        .expect("stdin should be writable");
+    child.wait_with_output().expect("binary should finish")
+}
+
+#[test]
+fn formats_stdin_to_stdout() {
+    let input = "SELECT count(*),Column1 FROM Table1;";
+    let expected = sqlformat::format(
+        input,
+        &sqlformat::QueryParams::None,
+        &sqlformat::FormatOptions::default(),
+    );
+
+    for args in [&["-"][..], &[]] {
+        let output = run_with_stdin(args, input.as_bytes());
+
+        assert!(output.status.success(), "{args:?}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            expected,
+            "{args:?}"
+        );
+    }
+}
+
+#[test]
+fn exits_non_zero_for_invalid_utf8_on_stdin() {
+    let output = run_with_stdin(&["-"], b"SELECT '\xff' FROM t;");
 
     assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
 
     let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("Usage:"));
+    assert!(stderr.contains("invalid UTF-8"));
 }
 
 #[test]
@@ -73,3 +111,341 @@ fn formats_file_in_place_with_default_options() {
 
     fs::remove_file(&sql_path).expect("test output file should be removable");
 }
+
+#[test]
+fn prints_help_and_version() {
+    let output = Command::new(sqlformat_bin())
+        .arg("--help")
+        .output()
+        .expect("binary should execute");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Usage:"));
+    assert!(stdout.contains("--max-inline-arguments"));
+
+    let output = Command::new(sqlformat_bin())
+        .arg("--version")
+        .output()
+        .expect("binary should execute");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.trim(),
+        format!("sqlformat {}", env!("CARGO_PKG_VERSION"))
+    );
+}
+
+#[test]
+fn formats_file_in_place_with_given_options() {
+    let sql_path = unique_temp_file_path("options");
+    let input = "select count(*),Column1 from Table1 left join Table2 on Table1.id = Table2.id;";
+    fs::write(&sql_path, input).expect("test input file should be writable");
+
+    let output = Command::new(sqlformat_bin())
+        .args([
+            "--indent=4",
+            "--uppercase",
+            "--joins-as-top-level",
+            "--dialect",
+            "postgresql",
+            "--ignore-case-convert",
+            "from,on",
+        ])
+        .arg(&sql_path)
+        .output()
+        .expect("binary should execute");
+
+    assert!(output.status.success());
+
+    let expected = sqlformat::format(
+        input,
+        &sqlformat::QueryParams::None,
+        &sqlformat::FormatOptions {
+            indent: sqlformat::Indent::Spaces(4),
+            uppercase: Some(true),
+            joins_as_top_level: true,
+            dialect: sqlformat::Dialect::PostgreSql,
+            ignore_case_convert: Some(vec!["from", "on"]),
+            ..Default::default()
+        },
+    );
+    let actual = fs::read_to_string(&sql_path).expect("formatted file should be readable");
+    assert_eq!(actual, expected);
+
+    fs::remove_file(&sql_path).expect("test output file should be removable");
+}
+
+#[test]
+fn exits_non_zero_for_invalid_options() {
+    for args in [
+        &["--unknown"][..],
+        &["--indent", "four"],
+        &["--dialect", "cobol"],
+        &["--max-inline-block"],
+    ] {
+        let output = Command::new(sqlformat_bin())
+            .args(args)
+            .output()
+            .expect("binary should execute");
+
+        assert!(!output.status.success(), "{args:?}");
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Usage:"), "{args:?}");
+    }
+}
+
+#[test]
+fn check_reports_unformatted_file_without_rewriting_it() {
+    let sql_path = unique_temp_file_path("check");
+    let input = "SELECT count(*),Column1 FROM Table1;";
+    fs::write(&sql_path, input).expect("test input file should be writable");
+
+    let output = Command::new(sqlformat_bin())
+        .arg("--check")
+        .arg(&sql_path)
+        .output()
+        .expect("binary should execute");
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim(), sql_path.display().to_string());
+    let actual = fs::read_to_string(&sql_path).expect("input file should be readable");
+    assert_eq!(actual, input);
+
+    let formatted = sqlformat::format(
+        input,
+        &sqlformat::QueryParams::None,
+        &sqlformat::FormatOptions::default(),
+    );
+    fs::write(&sql_path, &formatted).expect("test input file should be writable");
+
+    let output = Command::new(sqlformat_bin())
+        .arg("--check")
+        .arg(&sql_path)
+        .output()
+        .expect("binary should execute");
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    fs::remove_file(&sql_path).expect("test output file should be removable");
+}
+
+#[test]
+fn diff_prints_unified_diff_of_the_changes() {
+    let input = "SELECT\n  a,\n  b\nFROM\n  t\nWHERE x=1;\n";
+    let output = run_with_stdin(&["--diff", "-"], input.as_bytes());
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout,
+        "--- <stdin>\n\
+         +++ <stdin>\n\
+         @@ -3,4 +3,5 @@\n\
+         \x20  b\n\
+         \x20FROM\n\
+         \x20  t\n\
+         -WHERE x=1;\n\
+         +WHERE\n\
+         +  x = 1;\n\
+         \\ No newline at end of file\n"
+    );
+}
+
+#[test]
+fn formats_directories_recursively_honoring_ignore_files() {
+    let dir = unique_temp_file_path("walk").with_extension("");
+    let input = "SELECT count(*),Column1 FROM Table1;";
+    let formatted = sqlformat::format(
+        input,
+        &sqlformat::QueryParams::None,
+        &sqlformat::FormatOptions::default(),
+    );
+
+    let files = [
+        "a.sql",
+        "nested/b.sql",
+        "nested/deeper/c.SQL",
+        "nested/skip.txt",
+        "nested/generated/d.sql",
+        "nested/generated/keep.sql",
+        "vendor/e.sql",
+        "f.psql",
+    ];
+    for file in files {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).expect("test directory should be creatable");
+        fs::write(&path, input).expect("test input file should be writable");
+    }
+    fs::write(dir.join(".sqlformatignore"), "# comment\n/vendor/\n").unwrap();
+    fs::write(
+        dir.join("nested/.sqlformatignore"),
+        "generated/*.sql\n!generated/keep.sql\n",
+    )
+    .unwrap();
+
+    let output = Command::new(sqlformat_bin())
+        .arg(&dir)
+        .output()
+        .expect("binary should execute");
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("4 files: 4 changed, 0 unchanged, 0 failed"));
+
+    for (file, expect_formatted) in [
+        ("a.sql", true),
+        ("nested/b.sql", true),
+        ("nested/deeper/c.SQL", true),
+        ("nested/skip.txt", false),
+        ("nested/generated/d.sql", false),
+        ("nested/generated/keep.sql", true),
+        ("vendor/e.sql", false),
+        ("f.psql", false),
+    ] {
+        let actual = fs::read_to_string(dir.join(file)).unwrap();
+        let expected = if expect_formatted { &formatted } else { input };
+        assert_eq!(actual, expected, "{file}");
+    }
+
+    let output = Command::new(sqlformat_bin())
+        .args(["--check", "--extensions", "sql,psql"])
+        .arg(&dir)
+        .arg(dir.join("missing.sql"))
+        .output()
+        .expect("binary should execute");
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim(), dir.join("f.psql").display().to_string());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Error reading"));
+    assert!(stderr.contains("6 files: 1 changed, 4 unchanged, 1 failed"));
+
+    fs::remove_dir_all(&dir).expect("test directory should be removable");
+}
+
+#[test]
+fn expands_quoted_glob_patterns() {
+    let dir = unique_temp_file_path("glob").with_extension("");
+    let input = "SELECT count(*),Column1 FROM Table1;";
+    for file in ["2024/01_init.sql", "2024/02_data.sql", "2025/01_init.sql"] {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).expect("test directory should be creatable");
+        fs::write(&path, input).expect("test input file should be writable");
+    }
+
+    let output = Command::new(sqlformat_bin())
+        .arg("--check")
+        .arg(format!("{}/**/01_*.sql", dir.display()))
+        .output()
+        .expect("binary should execute");
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let listed: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        listed,
+        [
+            dir.join("2024/01_init.sql").display().to_string(),
+            dir.join("2025/01_init.sql").display().to_string(),
+        ]
+    );
+
+    fs::remove_dir_all(&dir).expect("test directory should be removable");
+}
+
+#[test]
+fn reads_options_from_the_closest_configuration_file() {
+    let dir = unique_temp_file_path("config").with_extension("");
+    let input = "select count(*),Column1 from Table1;";
+    let nested = dir.join("nested/deeper");
+    fs::create_dir_all(&nested).expect("test directory should be creatable");
+    fs::write(
+        dir.join("sqlformat.toml"),
+        "indent = 4\nuppercase = true\ndialect = \"postgresql\"\n",
+    )
+    .unwrap();
+    fs::write(nested.join("query.sql"), input).unwrap();
+
+    let output = Command::new(sqlformat_bin())
+        .arg("--lowercase")
+        .arg(nested.join("query.sql"))
+        .output()
+        .expect("binary should execute");
+
+    assert!(output.status.success());
+    let expected = sqlformat::format(
+        input,
+        &sqlformat::QueryParams::None,
+        &sqlformat::FormatOptions {
+            indent: sqlformat::Indent::Spaces(4),
+            uppercase: Some(false),
+            dialect: sqlformat::Dialect::PostgreSql,
+            ..Default::default()
+        },
+    );
+    let actual = fs::read_to_string(nested.join("query.sql")).unwrap();
+    assert_eq!(actual, expected);
+
+    fs::write(
+        dir.join("nested/sqlformat.toml"),
+        "indent = 4\nindnet = 2\n",
+    )
+    .unwrap();
+    let output = Command::new(sqlformat_bin())
+        .arg(nested.join("query.sql"))
+        .output()
+        .expect("binary should execute");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("line 2: unknown key `indnet`"));
+
+    let output = Command::new(sqlformat_bin())
+        .arg("--config")
+        .arg(dir.join("sqlformat.toml"))
+        .arg(nested.join("query.sql"))
+        .output()
+        .expect("binary should execute");
+
+    assert!(output.status.success());
+
+    fs::remove_dir_all(&dir).expect("test directory should be removable");
+}
+
+#[test]
+fn command_line_flags_override_configured_options() {
+    let dir = unique_temp_file_path("override").with_extension("");
+    let input = "select a from t join u on t.id = u.id;";
+    fs::create_dir_all(&dir).expect("test directory should be creatable");
+    fs::write(
+        dir.join("sqlformat.toml"),
+        "uppercase = true\ninline = true\njoins_as_top_level = true\n",
+    )
+    .unwrap();
+    fs::write(dir.join("query.sql"), input).unwrap();
+
+    let output = Command::new(sqlformat_bin())
+        .arg("--preserve-case")
+        .arg("--no-inline")
+        .arg("--no-joins-as-top-level")
+        .arg(dir.join("query.sql"))
+        .output()
+        .expect("binary should execute");
+
+    assert!(output.status.success());
+    let expected = sqlformat::format(
+        input,
+        &sqlformat::QueryParams::None,
+        &sqlformat::FormatOptions::default(),
+    );
+    let actual = fs::read_to_string(dir.join("query.sql")).unwrap();
+    assert_eq!(actual, expected);
+
+    fs::remove_dir_all(&dir).expect("test directory should be removable");
+}
@@ -0,0 +1,189 @@
+#![cfg(feature = "lsp")]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+
+fn unique_temp_dir(name: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock should be after unix epoch")
+        .as_nanos();
+
+    std::env::temp_dir().join(format!(
+        "sqlformat-lsp-{name}-{}-{nanos}",
+        std::process::id()
+    ))
+}
+
+/// Sends `messages` to the server followed by `shutdown` and `exit`, and returns the
+/// responses by request id
+fn run_session(messages: &[Value]) -> Vec<Value> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sqlformat-lsp"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("binary should execute");
+
+    let mut stdin = child.stdin.take().expect("stdin should be piped");
+    let shutdown = json!({ "jsonrpc": "2.0", "id": "shutdown", "method": "shutdown" });
+    let exit = json!({ "jsonrpc": "2.0", "method": "exit" });
+    for message in messages.iter().chain([&shutdown, &exit]) {
+        let body = message.to_string();
+        write!(stdin, "Content-Length: {}\r\n\r\n{body}", body.len())
+            .expect("stdin should be writable");
+    }
+    drop(stdin);
+
+    let output = child.wait_with_output().expect("binary should finish");
+    assert!(output.status.success());
+
+    let mut responses: Vec<Value> = Vec::new();
+    let mut rest = std::str::from_utf8(&output.stdout).expect("output should be UTF-8");
+    while let Some((header, body)) = rest.split_once("\r\n\r\n") {
+        let length: usize = header
+            .strip_prefix("Content-Length: ")
+            .and_then(|length| length.parse().ok())
+            .expect("message should have a length");
+        responses.push(serde_json::from_str(&body[..length]).expect("body should be JSON"));
+        rest = &body[length..];
+    }
+    assert_eq!(responses.last().unwrap()["id"], "shutdown");
+    responses
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+#[test]
+fn formats_documents_ranges_and_typed_statements() {
+    let uri = "untitled:query.sql";
+    let text = "SELECT a, b FROM t;\n  select 'é', c from u;";
+    let document = json!({ "uri": uri });
+    let options = json!({ "tabSize": 4, "insertSpaces": true });
+
+    let responses = run_session(&[
+        request(1, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": uri, "languageId": "sql", "version": 1, "text": "" } }),
+        ),
+        notification(
+            "textDocument/didChange",
+            json!({ "textDocument": { "uri": uri, "version": 2 }, "contentChanges": [{ "text": text }] }),
+        ),
+        request(
+            2,
+            "textDocument/formatting",
+            json!({ "textDocument": document, "options": options }),
+        ),
+        request(
+            3,
+            "textDocument/rangeFormatting",
+            json!({
+                "textDocument": document,
+                "options": options,
+                "range": { "start": { "line": 1, "character": 14 }, "end": { "line": 1, "character": 14 } },
+            }),
+        ),
+        request(
+            4,
+            "textDocument/onTypeFormatting",
+            json!({
+                "textDocument": document,
+                "options": { "tabSize": 2, "insertSpaces": false },
+                "position": { "line": 0, "character": 19 },
+                "ch": ";",
+            }),
+        ),
+        request(5, "textDocument/hover", json!({})),
+    ]);
+
+    assert_eq!(responses.len(), 6);
+    let capabilities = &responses[0]["result"]["capabilities"];
+    assert_eq!(capabilities["documentFormattingProvider"], true);
+    assert_eq!(capabilities["documentRangeFormattingProvider"], true);
+    assert_eq!(
+        capabilities["documentOnTypeFormattingProvider"]["firstTriggerCharacter"],
+        ";"
+    );
+
+    assert_eq!(
+        responses[1]["result"],
+        json!([{
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 1, "character": 23 } },
+            "newText": "SELECT\n    a,\n    b\nFROM\n    t;\nselect\n    'é',\n    c\nfrom\n    u;",
+        }])
+    );
+    assert_eq!(
+        responses[2]["result"],
+        json!([{
+            "range": { "start": { "line": 1, "character": 2 }, "end": { "line": 1, "character": 23 } },
+            "newText": "select\n      'é',\n      c\n  from\n      u;",
+        }])
+    );
+    assert_eq!(
+        responses[3]["result"],
+        json!([{
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 19 } },
+            "newText": "SELECT\n\ta,\n\tb\nFROM\n\tt;",
+        }])
+    );
+    assert_eq!(responses[4]["error"]["code"], -32601);
+}
+
+#[test]
+fn reads_options_from_client_settings_and_configuration_files() {
+    let dir = unique_temp_dir("config");
+    fs::create_dir_all(dir.join("nested")).expect("directory should be creatable");
+    fs::write(dir.join("sqlformat.toml"), "uppercase = true\n").expect("config should be writable");
+    let configured = format!("file://{}", dir.join("nested/query.sql").display());
+    let untitled = "untitled:query.sql";
+
+    let open = |uri: &str| {
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": uri, "languageId": "sql", "version": 1, "text": "select a from t" } }),
+        )
+    };
+    let format = |id, uri: &str| {
+        request(
+            id,
+            "textDocument/formatting",
+            json!({ "textDocument": { "uri": uri }, "options": { "tabSize": 4, "insertSpaces": true } }),
+        )
+    };
+    let responses = run_session(&[
+        request(
+            1,
+            "initialize",
+            json!({ "capabilities": {}, "initializationOptions": { "sqlformat": { "uppercase": false, "indent": 2 } } }),
+        ),
+        open(&configured),
+        open(untitled),
+        format(2, &configured),
+        format(3, untitled),
+        notification(
+            "workspace/didChangeConfiguration",
+            json!({ "settings": { "inline": true } }),
+        ),
+        format(4, untitled),
+    ]);
+    fs::remove_dir_all(&dir).expect("directory should be removable");
+
+    let new_text = |response: &Value| response["result"][0]["newText"].clone();
+    assert_eq!(new_text(&responses[1]), "SELECT\n  a\nFROM\n  t");
+    assert_eq!(new_text(&responses[2]), "select\n  a\nfrom\n  t");
+    assert_eq!(responses[3]["result"], json!([]));
+}